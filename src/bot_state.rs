@@ -0,0 +1,47 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::prelude::*;
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+/// Bot settings changed from Discord, kept across bot restarts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BotState {
+    #[serde(default)]
+    pub maintenance: bool,
+    /// The server status when it last changed, see `ServerStatus::name`.
+    #[serde(default)]
+    pub server_status: Option<String>,
+    /// The server process the bot was managing, if any.
+    #[serde(default)]
+    pub server_pid: Option<u32>,
+}
+
+impl BotState {
+    /// Loads the saved state, starting from defaults if the file is missing or broken.
+    pub fn load(path: &str) -> BotState {
+        let read_state = || -> Result<BotState, Box<dyn Error>> {
+            let mut file = File::open(path)?;
+            let mut state_str = String::new();
+            file.read_to_string(&mut state_str)?;
+
+            Ok(serde_json::from_str(state_str.as_str())?)
+        };
+
+        match read_state() {
+            Ok(state) => state,
+            Err(x) => {
+                warn!("Could not load bot state from {}, starting fresh - {}", path, x);
+                BotState::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+
+        Ok(())
+    }
+}
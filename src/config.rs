@@ -0,0 +1,475 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use chrono::Duration;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::jvm_flags;
+use crate::server_log::scanners::strip_bedrock_prefix;
+
+pub const DEFAULT_PREFIX: &str = "mc!";
+
+/// The config as the Discord thread sees it, replaced as a whole when the config is reloaded.
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+/// Which server events are relayed to Discord, the `relay` section of the config.
+/// Tracking, like the online players and stats, happens either way.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RelayConfig {
+    pub channel: Option<u64>,
+    pub chat: bool,
+    pub joins_leaves: bool,
+    pub deaths: bool,
+    pub advancements: bool,
+    pub moderation: bool,
+    pub backups: bool,
+    pub lag_spikes: bool,
+    pub errors: bool,
+    /// Off unless asked for, it sends everything said in the relay channel into the game.
+    pub discord_chat: bool,
+}
+
+impl Default for RelayConfig {
+    fn default() -> RelayConfig {
+        RelayConfig {
+            channel: None,
+            chat: true,
+            joins_leaves: true,
+            deaths: true,
+            advancements: true,
+            moderation: true,
+            backups: true,
+            lag_spikes: true,
+            errors: true,
+            discord_chat: false,
+        }
+    }
+}
+
+/// `start_trigger` is either a message to look for, or a message and the exact sender that logs it.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum StartTriggerConfig {
+    Message(String),
+    Sender {
+        sender: Option<String>,
+        message: String,
+    },
+}
+
+/// The config file, read once at startup and again on every reload.
+/// Missing keys get their defaults, `preflight::validate_config` reports the required ones that were left empty.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub token: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+
+    pub channel_id: u64,
+    pub prefix: String,
+    pub channels: Vec<u64>,
+    pub admin_channel: Option<u64>,
+    pub owner_id: Option<u64>,
+    pub admin_users: Vec<u64>,
+    pub admin_roles: Vec<u64>,
+    pub rcon_users: Vec<u64>,
+    pub rate_limit_messages: u32,
+    pub rate_limit_seconds: u64,
+    pub presence: bool,
+    pub heartbeat_minutes: i64,
+
+    #[serde(rename = "java-path")]
+    pub java_path: String,
+    #[serde(rename = "server-path")]
+    pub server_path: String,
+    #[serde(rename = "server-folder")]
+    pub server_folder: String,
+    #[serde(rename = "min-ram")]
+    pub min_ram: String,
+    #[serde(rename = "max-ram")]
+    pub max_ram: String,
+    pub jvm_preset: Option<String>,
+    pub jvm_args: Vec<String>,
+    /// Worked out from `jvm_preset` and `jvm_args` when the config is read.
+    #[serde(skip)]
+    pub jvm_flags: Vec<String>,
+    pub managed: bool,
+    /// `pipe` or a log file relative to the server folder, see `log_source_file`.
+    pub log_source: Option<String>,
+    pub start_trigger: Option<StartTriggerConfig>,
+    pub startup_timeout_minutes: i64,
+    pub startup_timeout_kill: bool,
+    /// Minutes before a scheduled shutdown to warn players at.
+    pub restart_warning_minutes: Vec<i64>,
+    pub preflight_strict: bool,
+    pub maintenance_message: String,
+
+    #[serde(rename = "mcrcon-path")]
+    pub mcrcon_path: String,
+    pub rcon_password: String,
+    pub rcon_port: u16,
+    pub rcon_allow_newlines: bool,
+
+    pub backup_folder: Option<String>,
+    pub backup_sync_command: Option<String>,
+    pub update_command: Option<String>,
+    pub update_log: String,
+    pub update_show_output: bool,
+
+    pub metrics_enabled: bool,
+    pub metrics_address: String,
+    pub metrics_port: u16,
+
+    pub state_file: String,
+    pub stats_file: String,
+    pub playtime_file: String,
+
+    pub join_message: String,
+    pub leave_message: String,
+    pub welcome_message: Option<String>,
+    pub goodbye_message: Option<String>,
+    pub greeting_cooldown_minutes: i64,
+    pub bedrock_prefix: String,
+    pub color_codes: String,
+    pub relay: RelayConfig,
+    /// The old top-level switch, used when `relay.joins_leaves` isn't set.
+    relay_joins_leaves: Option<bool>,
+    pub announce_first_player: bool,
+    pub announce_server_empty: bool,
+    pub empty_reminder_minutes: i64,
+
+    pub lag_threshold_ms: i64,
+    pub lag_threshold_ticks: usize,
+    pub lag_report_seconds: i64,
+    pub tps_command: String,
+    pub tps_alert_threshold: f64,
+    pub tps_alert_minutes: i64,
+    pub tps_poll_seconds: i64,
+
+    pub log_level: Option<String>,
+    #[serde(alias = "console-enabled")]
+    pub console_enabled: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            token: None,
+            username: None,
+            password: None,
+
+            channel_id: 0,
+            prefix: DEFAULT_PREFIX.to_string(),
+            channels: Vec::new(),
+            admin_channel: None,
+            owner_id: None,
+            admin_users: Vec::new(),
+            admin_roles: Vec::new(),
+            rcon_users: Vec::new(),
+            rate_limit_messages: 5,
+            rate_limit_seconds: 5,
+            presence: true,
+            heartbeat_minutes: 0,
+
+            java_path: String::new(),
+            server_path: String::new(),
+            server_folder: String::new(),
+            min_ram: String::new(),
+            max_ram: String::new(),
+            jvm_preset: None,
+            jvm_args: Vec::new(),
+            jvm_flags: Vec::new(),
+            managed: true,
+            log_source: None,
+            start_trigger: None,
+            startup_timeout_minutes: 5,
+            startup_timeout_kill: false,
+            restart_warning_minutes: vec![15, 5, 1],
+            preflight_strict: false,
+            maintenance_message: "The server is down for maintenance".to_string(),
+
+            mcrcon_path: String::new(),
+            rcon_password: String::new(),
+            rcon_port: 25564,
+            rcon_allow_newlines: false,
+
+            backup_folder: None,
+            backup_sync_command: None,
+            update_command: None,
+            update_log: "update.log".to_string(),
+            update_show_output: false,
+
+            metrics_enabled: false,
+            metrics_address: "127.0.0.1".to_string(),
+            metrics_port: 9225,
+
+            state_file: "state.json".to_string(),
+            stats_file: "stats.json".to_string(),
+            playtime_file: "playtime.json".to_string(),
+
+            join_message: "*{player} joined the game*".to_string(),
+            leave_message: "*{player} left the game*".to_string(),
+            welcome_message: None,
+            goodbye_message: None,
+            greeting_cooldown_minutes: 10,
+            bedrock_prefix: String::new(),
+            color_codes: "strip".to_string(),
+            relay: RelayConfig::default(),
+            relay_joins_leaves: None,
+            announce_first_player: false,
+            announce_server_empty: false,
+            empty_reminder_minutes: 0,
+
+            lag_threshold_ms: 0,
+            lag_threshold_ticks: 0,
+            lag_report_seconds: 60,
+            tps_command: "forge tps".to_string(),
+            tps_alert_threshold: 0.0,
+            tps_alert_minutes: 5,
+            tps_poll_seconds: 60,
+
+            log_level: None,
+            console_enabled: false,
+        }
+    }
+}
+
+/// Empty strings in the config mean the same as leaving the key out.
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|x| !x.trim().is_empty())
+}
+
+impl Config {
+    pub fn from_value(value: &Value) -> Result<Config, Box<dyn Error>> {
+        let mut config = Config::deserialize(value).map_err(|x| format!("Invalid config file - {}", x))?;
+
+        config.prefix = config.prefix.trim().to_string();
+        if config.prefix.is_empty() {
+            config.prefix = DEFAULT_PREFIX.to_string();
+        }
+
+        for (name, template) in &[("join_message", &config.join_message), ("leave_message", &config.leave_message)] {
+            if !template.contains("{player}") {
+                return Err(format!("{} in config file must contain {{player}}", name).into());
+            }
+        }
+
+        if value.get("relay").and_then(|relay| relay.get("joins_leaves")).is_none() {
+            if let Some(joins_leaves) = config.relay_joins_leaves {
+                config.relay.joins_leaves = joins_leaves;
+            }
+        }
+
+        config.token = non_empty(config.token.take());
+        config.log_source = non_empty(config.log_source.take());
+        config.welcome_message = non_empty(config.welcome_message.take());
+        config.goodbye_message = non_empty(config.goodbye_message.take());
+        config.backup_sync_command = non_empty(config.backup_sync_command.take());
+        config.update_command = non_empty(config.update_command.take());
+        if let Some(StartTriggerConfig::Message(message)) | Some(StartTriggerConfig::Sender { message, .. }) = &config.start_trigger {
+            if message.is_empty() {
+                config.start_trigger = None;
+            }
+        }
+
+        config.jvm_flags = jvm_flags::jvm_flags(&config)?;
+
+        Ok(config)
+    }
+
+    pub fn log_level(&self) -> log::LevelFilter {
+        match &self.log_level {
+            Some(level) => level.parse().unwrap_or_else(|_| {
+                // The logger isn't up yet
+                eprintln!("Unknown log_level {}, expected trace, debug, info, warn or error", level);
+                log::LevelFilter::Info
+            }),
+            None => log::LevelFilter::Info,
+        }
+    }
+
+    pub fn empty_reminder_timeout(&self) -> Option<Duration> {
+        Some(self.empty_reminder_minutes).filter(|&x| x > 0).map(Duration::minutes)
+    }
+
+    pub fn lag_report_window(&self) -> Duration {
+        Duration::seconds(self.lag_report_seconds)
+    }
+
+    pub fn startup_timeout(&self) -> Option<Duration> {
+        Some(self.startup_timeout_minutes).filter(|&x| x > 0).map(Duration::minutes)
+    }
+
+    pub fn color_codes_markdown(&self) -> bool {
+        self.color_codes == "markdown"
+    }
+
+    pub fn tps_alert_threshold(&self) -> Option<f64> {
+        Some(self.tps_alert_threshold).filter(|&x| x > 0.0)
+    }
+
+    pub fn tps_alert_window(&self) -> Duration {
+        Duration::minutes(self.tps_alert_minutes)
+    }
+
+    pub fn tps_poll_interval(&self) -> Duration {
+        Duration::seconds(self.tps_poll_seconds.max(5))
+    }
+
+    pub fn greeting_cooldown(&self) -> Duration {
+        Duration::minutes(self.greeting_cooldown_minutes)
+    }
+
+    /// The restart warning offsets, furthest first.
+    pub fn restart_warnings(&self) -> Vec<Duration> {
+        let mut warnings: Vec<Duration> = self.restart_warning_minutes.iter().copied()
+            .filter(|&x| x > 0)
+            .map(Duration::minutes)
+            .collect();
+        warnings.sort_by(|a, b| b.cmp(a));
+        warnings.dedup();
+        warnings
+    }
+
+    pub fn heartbeat_interval(&self) -> Option<Duration> {
+        Some(self.heartbeat_minutes).filter(|&x| x > 0).map(Duration::minutes)
+    }
+
+    /// Lag spikes below either threshold aren't worth a Discord message.
+    pub fn is_lag_reportable(&self, length: Duration, ticks: usize) -> bool {
+        length >= Duration::milliseconds(self.lag_threshold_ms) && ticks >= self.lag_threshold_ticks
+    }
+
+    /// The name shown on Discord, marking Bedrock players instead of showing their prefix.
+    pub fn display_name(&self, name: &str) -> String {
+        match strip_bedrock_prefix(name, &self.bedrock_prefix) {
+            Some(bedrock_name) => format!("{} (Bedrock)", bedrock_name),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// Written by hand, so the token and passwords don't end up in the logs.
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let redacted = |secret: &Option<String>| secret.as_ref().map(|_| "<redacted>");
+
+        f.debug_struct("Config")
+            .field("token", &redacted(&self.token))
+            .field("username", &self.username)
+            .field("password", &redacted(&self.password))
+            .field("channel_id", &self.channel_id)
+            .field("prefix", &self.prefix)
+            .field("java_path", &self.java_path)
+            .field("server_path", &self.server_path)
+            .field("server_folder", &self.server_folder)
+            .field("mcrcon_path", &self.mcrcon_path)
+            .field("rcon_password", &"<redacted>")
+            .field("rcon_port", &self.rcon_port)
+            .field("managed", &self.managed)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_from_value() {
+        let config = Config::from_value(&serde_json::json!({
+            "token": "abc",
+            "channel_id": 123,
+            "java-path": "java",
+            "server-folder": "server",
+            "mcrcon-path": "mcrcon",
+            "rcon_password": "pass",
+            "welcome_message": "",
+            "unrelated": true,
+        })).unwrap();
+
+        assert_eq!(config.token.as_deref(), Some("abc"));
+        assert_eq!(config.channel_id, 123);
+        assert_eq!(config.java_path, "java");
+        assert_eq!(config.server_path, "");
+        assert_eq!(config.rcon_port, 25564);
+        assert_eq!(config.prefix, DEFAULT_PREFIX);
+        assert_eq!(config.welcome_message, None);
+
+        assert!(Config::from_value(&serde_json::json!({ "channel_id": "general" })).is_err());
+        assert!(Config::from_value(&serde_json::json!({ "rcon_port": 70000 })).is_err());
+        assert!(Config::from_value(&serde_json::json!({ "join_message": "hi" })).is_err());
+    }
+
+    #[test]
+    fn test_config_debug_redacts_secrets() {
+        let config = Config::from_value(&serde_json::json!({
+            "token": "bot_token_value",
+            "rcon_password": "rcon_password_value",
+        })).unwrap();
+
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("bot_token_value"));
+        assert!(!debug.contains("rcon_password_value"));
+    }
+
+    #[test]
+    fn test_log_level() {
+        let log_level = |value: Value| Config::from_value(&value).unwrap().log_level();
+
+        assert_eq!(log_level(serde_json::json!({})), log::LevelFilter::Info);
+        assert_eq!(log_level(serde_json::json!({ "log_level": "DEBUG" })), log::LevelFilter::Debug);
+        assert_eq!(log_level(serde_json::json!({ "log_level": "warn" })), log::LevelFilter::Warn);
+        assert_eq!(log_level(serde_json::json!({ "log_level": "loud" })), log::LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_lag_threshold() {
+        let config = Config::from_value(&serde_json::json!({
+            "lag_threshold_ms": 2000,
+            "lag_threshold_ticks": 40
+        })).unwrap();
+
+        assert!(config.is_lag_reportable(Duration::milliseconds(5125), 102));
+        assert!(!config.is_lag_reportable(Duration::milliseconds(1500), 102));
+        assert!(!config.is_lag_reportable(Duration::milliseconds(5125), 20));
+    }
+
+    #[test]
+    fn test_startup_timeout_default() {
+        assert_eq!(Config::from_value(&serde_json::json!({})).unwrap().startup_timeout(), Some(Duration::minutes(5)));
+        assert_eq!(Config::from_value(&serde_json::json!({ "startup_timeout_minutes": 0 })).unwrap().startup_timeout(), None);
+    }
+
+    #[test]
+    fn test_restart_warnings() {
+        assert_eq!(Config::from_value(&serde_json::json!({})).unwrap().restart_warnings(),
+            vec![Duration::minutes(15), Duration::minutes(5), Duration::minutes(1)]);
+        assert_eq!(Config::from_value(&serde_json::json!({ "restart_warning_minutes": [1, 10, 0, 10] })).unwrap().restart_warnings(),
+            vec![Duration::minutes(10), Duration::minutes(1)]);
+    }
+
+    #[test]
+    fn test_relay_config() {
+        let relay = Config::from_value(&serde_json::json!({
+            "relay_joins_leaves": false,
+            "relay": { "chat": false, "channel": 767400000000000001u64 }
+        })).unwrap().relay;
+
+        assert_eq!(relay.channel, Some(767400000000000001));
+        assert!(!relay.chat);
+        assert!(!relay.joins_leaves);
+        assert!(relay.deaths && relay.moderation && relay.backups && relay.lag_spikes && relay.errors);
+        assert!(!relay.discord_chat);
+
+        let relay = Config::from_value(&serde_json::json!({
+            "relay_joins_leaves": false,
+            "relay": { "joins_leaves": true }
+        })).unwrap().relay;
+        assert!(relay.joins_leaves);
+    }
+}
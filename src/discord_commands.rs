@@ -1,89 +1,423 @@
 use std::error::Error;
+use std::fmt;
+use std::sync::PoisonError;
 
-use crossbeam::channel::Sender;
-use discord::{State, Connection};
-use discord::model::{Event};
+use crossbeam::channel::{Receiver, Sender};
+use discord::{ChannelRef, State, Connection};
+use discord::model::{ChannelId, Event, MessageId, ReactionEmoji, RoleId, UserId};
 use log::*;
-use serde_json::Value;
+use crate::config::{Config, SharedConfig};
 
+#[derive(Debug)]
 pub enum FromDiscord {
     ReconnectEvent,
     ErrorEvent,
     StartServerEvent,
     StopServerEvent,
     KillServerEvent,
+    RestartServerEvent,
     ShutdownServerEvent(u8, u8),
-    CancelShutdownEvent,
+    InvalidTimeEvent(String),
+    /// A known command with missing or wrong arguments, `usage` is what should follow the command.
+    UsageError {
+        command: String,
+        usage: String
+    },
+    CancelShutdownEvent(String),
     BackupEvent,
+    BackupStatsQueryEvent,
     OpCommandEvent(String),
+    DeopCommandEvent(String),
+    WhitelistAddEvent(String),
+    WhitelistRemoveEvent(String),
+    RawRconEvent(String),
     StatusQueryEvent,
+    PlayersQueryEvent,
+    TpsQueryEvent,
+    PlaytimeQueryEvent(String),
+    LeaderboardQueryEvent,
+    MemoryQueryEvent,
+    ResourceQueryEvent,
+    MotdEvent(String),
+    ScheduleQueryEvent,
+    RestartWarningQueryEvent,
+    MaintenanceEvent(Option<bool>),
+    UpdateEvent,
+    RestartReactionEvent(MessageId),
+    /// The "Server died" message got sent, sent by the thread sending messages.
+    RestartOfferedEvent(MessageId),
+    ChatRelay {
+        author: String,
+        content: String
+    },
+    ReloadConfigEvent,
+    VersionQueryEvent,
     HelpEvent,
+    NotAllowedEvent,
+    UnauthorizedEvent,
     UnknownCommand,
     NoCommand
 }
 
-pub fn discord_thread(_config: Value, mut connection: Connection, state: State, discord_send: Sender<FromDiscord>) -> Result<(), Box<dyn Error>> {
+/// The Discord user who sent a command, for the audit log.
+#[derive(Clone, Debug)]
+pub struct Issuer {
+    pub name: String,
+    pub id: UserId,
+}
+
+impl fmt::Display for Issuer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Reactions only come with an ID, the name is missing if the member isn't cached
+        if self.name.is_empty() {
+            write!(f, "user {}", self.id.0)
+        } else {
+            write!(f, "{} ({})", self.name, self.id.0)
+        }
+    }
+}
+
+impl FromDiscord {
+    fn usage(command: &str, usage: &str) -> FromDiscord {
+        FromDiscord::UsageError { command: command.to_string(), usage: usage.to_string() }
+    }
+
+    /// Commands that change the server, only accepted in the admin channel if there is one.
+    pub fn is_admin_only(&self) -> bool {
+        if let FromDiscord::MotdEvent(motd) = self {
+            return !motd.is_empty();
+        }
+
+        matches!(self,
+            FromDiscord::StartServerEvent
+            | FromDiscord::StopServerEvent
+            | FromDiscord::KillServerEvent
+            | FromDiscord::RestartServerEvent
+            | FromDiscord::ShutdownServerEvent(..)
+            | FromDiscord::CancelShutdownEvent(..)
+            | FromDiscord::BackupEvent
+            | FromDiscord::OpCommandEvent(..)
+            | FromDiscord::DeopCommandEvent(..)
+            | FromDiscord::WhitelistAddEvent(..)
+            | FromDiscord::WhitelistRemoveEvent(..)
+            | FromDiscord::RawRconEvent(..)
+            | FromDiscord::ReloadConfigEvent
+            | FromDiscord::MaintenanceEvent(Some(_))
+            | FromDiscord::UpdateEvent
+            | FromDiscord::ResourceQueryEvent
+            | FromDiscord::RestartReactionEvent(..)
+        )
+    }
+}
+
+/// Parses a 24-hour `hh:mm` time, or says what's wrong with it.
+pub fn parse_time(time: &str) -> Result<(u8, u8), String> {
+    let (hour, minute) = time.split_once(':').ok_or_else(|| format!("`{}` should look like hh:mm", time))?;
+
+    let hour = hour.parse::<u8>().ok().filter(|x| *x < 24)
+        .ok_or_else(|| format!("Hour `{}` should be between 0 and 23", hour))?;
+    let minute = minute.parse::<u8>().ok().filter(|x| *x < 60)
+        .ok_or_else(|| format!("Minute `{}` should be between 0 and 59", minute))?;
+
+    Ok((hour, minute))
+}
+
+/// Who may run admin commands, from `admin_users` and `admin_roles` in the config.
+/// If neither is set, everyone can, like before they existed. The owner always can.
+pub struct Admins {
+    owner: Option<UserId>,
+    users: Vec<UserId>,
+    roles: Vec<RoleId>,
+}
+
+impl Admins {
+    pub fn from_config(config: &Config) -> Admins {
+        Admins {
+            owner: config.owner_id.map(UserId),
+            users: config.admin_users.iter().copied().map(UserId).collect(),
+            roles: config.admin_roles.iter().copied().map(RoleId).collect(),
+        }
+    }
+
+    pub fn allows(&self, user: UserId, roles: &[RoleId]) -> bool {
+        (self.users.is_empty() && self.roles.is_empty())
+            || self.owner == Some(user)
+            || self.users.contains(&user)
+            || roles.iter().any(|role| self.roles.contains(role))
+    }
+}
+
+/// The roles of `user` in the server `channel` is in, as far as the cached state knows. None in DMs.
+fn member_roles(state: &State, channel: ChannelId, user: UserId) -> Vec<RoleId> {
+    match state.find_channel(channel) {
+        Some(ChannelRef::Public(server, _)) => server.members.iter()
+            .find(|member| member.user.id == user)
+            .map(|member| member.roles.clone())
+            .unwrap_or_default(),
+        _ => Vec::new()
+    }
+}
+
+/// The name of a user in the server the channel belongs to, empty if they're not cached.
+fn member_name(state: &State, channel: ChannelId, user: UserId) -> String {
+    match state.find_channel(channel) {
+        Some(ChannelRef::Public(server, _)) => server.members.iter()
+            .find(|member| member.user.id == user)
+            .map(|member| member.user.name.clone())
+            .unwrap_or_default(),
+        _ => String::new()
+    }
+}
+
+/// Reacting with this on a "Server died" message restarts the server.
+pub const RESTART_EMOJI: &str = "🔄";
+
+/// Where the bot listens and who may do what, taken from the config for each event so a reload applies right away.
+struct Listening {
+    admin_channel: Option<ChannelId>,
+    channels: Vec<ChannelId>,
+    relay_channel: ChannelId,
+    owner: Option<UserId>,
+    admins: Admins,
+    // Raw RCON can do anything on the server, so it's limited to these users and the owner
+    rcon_users: Vec<UserId>,
+    prefix: String,
+}
+
+impl Listening {
+    fn from_config(config: &Config, bot_channel: ChannelId) -> Listening {
+        let admin_channel = config.admin_channel.map(ChannelId);
+        let mut channels: Vec<ChannelId> = if config.channels.is_empty() {
+            vec![bot_channel]
+        } else {
+            config.channels.iter().copied().map(ChannelId).collect()
+        };
+        channels.extend(admin_channel);
+
+        Listening {
+            admin_channel,
+            channels,
+            relay_channel: config.relay.channel.map(ChannelId).unwrap_or(bot_channel),
+            owner: config.owner_id.map(UserId),
+            admins: Admins::from_config(config),
+            rcon_users: config.rcon_users.iter().copied().map(UserId).collect(),
+            prefix: config.prefix.clone(),
+        }
+    }
+}
+
+pub fn discord_thread(config: SharedConfig, mut connection: Connection, mut state: State, bot_channel: ChannelId, presence: Receiver<&'static str>, discord_send: Sender<(ChannelId, Option<Issuer>, FromDiscord)>) -> Result<(), Box<dyn Error>> {
     info!("Discord thread now running.");
 
     loop {
+        // The connection is only reachable from this thread between events. Status changes come with
+        // a message from the bot, and the bot's own messages are events too, so they show up right away.
+        if let Some(status) = presence.try_iter().last() {
+            connection.set_game_name(format!("Server: {}", status));
+        }
+
         let event = match connection.recv_event() {
             Ok(event) => event,
             Err(err) => {
                 error!("Receive error: {}", err);
 
                 if let discord::Error::WebSocket(..) = err {
-                    discord_send.send(FromDiscord::ReconnectEvent)?;
+                    discord_send.send((bot_channel, None, FromDiscord::ReconnectEvent))?;
                     return Ok(());
                 }
 
                 if let discord::Error::Closed(..) = err {
-                    discord_send.send(FromDiscord::ErrorEvent)?;
+                    discord_send.send((bot_channel, None, FromDiscord::ErrorEvent))?;
                     return Ok(());
                 }
                 continue;
             }
         };
 
+        state.update(&event);
+
+        let Listening { admin_channel, channels, relay_channel, owner, admins, rcon_users, prefix } =
+            Listening::from_config(&config.read().unwrap_or_else(PoisonError::into_inner), bot_channel);
+
         match event {
             Event::MessageCreate(message) => {
                 if message.author.id == state.user().id {
                     continue;
                 }
+
+                // Other bots could be relaying the game chat themselves
+                if message.channel_id == relay_channel && !message.author.bot && !message.content.starts_with(prefix.as_str()) {
+                    if !message.content.trim().is_empty() {
+                        discord_send.send((message.channel_id, None, FromDiscord::ChatRelay {
+                            author: message.author.name.clone(),
+                            content: message.content.clone()
+                        }))?;
+                    }
+                    continue;
+                }
+
+                let is_owner_dm = owner == Some(message.author.id)
+                    && state.private_channels().iter().any(|channel| channel.id == message.channel_id);
                 
-                if message.channel_id.0 != crate::BOT_CHANNEL {
+                if !is_owner_dm && !channels.contains(&message.channel_id) {
                     continue;
                 }
 
-                if !message.content.starts_with(crate::PREFIX) {
+                if !message.content.starts_with(prefix.as_str()) {
                     continue;
                 }
 
                 let message_params: Vec<String> = message.content
-                .split_at(crate::PREFIX.len()).1
+                .split_at(prefix.len()).1
                 .split_ascii_whitespace()
                 .map(String::from)
                 .collect();
 
-                discord_send.send(
-                    match message_params.first().map(String::as_str) {
-                        Some("start") => FromDiscord::StartServerEvent,
-                        Some("stop") => FromDiscord::StopServerEvent,
-                        Some("kill") => FromDiscord::KillServerEvent,
-                        
-                        Some("shutdown") => FromDiscord::ShutdownServerEvent(0, 0),
-                        Some("cancel") => FromDiscord::CancelShutdownEvent,
-                        Some("backup") => FromDiscord::BackupEvent,
-                        Some("op") => FromDiscord::OpCommandEvent(message_params.get(1).cloned().unwrap_or_default()),
-                        Some("status") => FromDiscord::StatusQueryEvent,
-
-                        Some("help") => FromDiscord::HelpEvent,
-
-                        Some(_x) => FromDiscord::UnknownCommand,
-                        None => FromDiscord::NoCommand
-                    }
-                )?;
+                let command = match message_params.first().map(String::as_str) {
+                    Some("start") => FromDiscord::StartServerEvent,
+                    Some("stop") => FromDiscord::StopServerEvent,
+                    Some("kill") => FromDiscord::KillServerEvent,
+                    Some("restart") => FromDiscord::RestartServerEvent,
+                    
+                    Some("shutdown") => match message_params.get(1).map(|time| parse_time(time)) {
+                        Some(Ok((hour, minute))) => FromDiscord::ShutdownServerEvent(hour, minute),
+                        Some(Err(x)) => FromDiscord::InvalidTimeEvent(x),
+                        None => FromDiscord::usage("shutdown", "<hh:mm>")
+                    },
+                    Some("cancel") => FromDiscord::CancelShutdownEvent(message.author.name.clone()),
+                    Some("backup") => FromDiscord::BackupEvent,
+                    Some("backups") => FromDiscord::BackupStatsQueryEvent,
+                    Some("op") => match message_params.get(1) {
+                        Some(user) => FromDiscord::OpCommandEvent(user.clone()),
+                        None => FromDiscord::usage("op", "<user>")
+                    },
+                    Some("deop") => match message_params.get(1) {
+                        Some(user) => FromDiscord::DeopCommandEvent(user.clone()),
+                        None => FromDiscord::usage("deop", "<user>")
+                    },
+                    Some("whitelist") => match (message_params.get(1).map(String::as_str), message_params.get(2)) {
+                        (Some("add"), Some(user)) => FromDiscord::WhitelistAddEvent(user.clone()),
+                        (Some("remove"), Some(user)) => FromDiscord::WhitelistRemoveEvent(user.clone()),
+                        _ => FromDiscord::usage("whitelist", "<add|remove> <user>")
+                    },
+                    Some("cmd") => match message.content[prefix.len()..].trim_start()["cmd".len()..].trim() {
+                        "" => FromDiscord::usage("cmd", "<command>"),
+                        command => FromDiscord::RawRconEvent(command.to_string())
+                    },
+                    Some("status") => FromDiscord::StatusQueryEvent,
+                    Some("players") => FromDiscord::PlayersQueryEvent,
+                    Some("tps") => FromDiscord::TpsQueryEvent,
+                    Some("playtime") => FromDiscord::PlaytimeQueryEvent(message_params.get(1).cloned().unwrap_or_default()),
+                    Some("leaderboard") => FromDiscord::LeaderboardQueryEvent,
+                    Some("memory") => FromDiscord::MemoryQueryEvent,
+                    Some("host") | Some("top") => FromDiscord::ResourceQueryEvent,
+                    Some("motd") => FromDiscord::MotdEvent(message_params[1..].join(" ")),
+                    Some("schedule") => FromDiscord::ScheduleQueryEvent,
+                    Some("restart-warn") => FromDiscord::RestartWarningQueryEvent,
+                    Some("update") => FromDiscord::UpdateEvent,
+                    Some("maintenance") => match message_params.get(1).map(String::as_str) {
+                        Some("on") => FromDiscord::MaintenanceEvent(Some(true)),
+                        Some("off") => FromDiscord::MaintenanceEvent(Some(false)),
+                        None => FromDiscord::MaintenanceEvent(None),
+                        Some(_) => FromDiscord::usage("maintenance", "[on|off]")
+                    },
+
+                    Some("reload") => FromDiscord::ReloadConfigEvent,
+                    Some("version") => FromDiscord::VersionQueryEvent,
+                    Some("help") => FromDiscord::HelpEvent,
+
+                    Some(_x) => FromDiscord::UnknownCommand,
+                    None => FromDiscord::NoCommand
+                };
+
+                let command = match admin_channel {
+                    Some(admin_channel) if command.is_admin_only() && message.channel_id != admin_channel && !is_owner_dm => FromDiscord::NotAllowedEvent,
+                    _ => command
+                };
+
+                let command = match command {
+                    command if command.is_admin_only()
+                        && !admins.allows(message.author.id, &member_roles(&state, message.channel_id, message.author.id)) => FromDiscord::UnauthorizedEvent,
+                    FromDiscord::RawRconEvent(..) if owner != Some(message.author.id) && !rcon_users.contains(&message.author.id) => FromDiscord::UnauthorizedEvent,
+                    _ => command
+                };
+
+                let issuer = Issuer { name: message.author.name.clone(), id: message.author.id };
+                discord_send.send((message.channel_id, Some(issuer), command))?;
+            },
+            Event::ReactionAdd(reaction) => {
+                if reaction.user_id == state.user().id || !channels.contains(&reaction.channel_id) {
+                    continue;
+                }
+
+                if reaction.emoji != ReactionEmoji::Unicode(RESTART_EMOJI.to_string()) {
+                    continue;
+                }
+
+                if matches!(admin_channel, Some(admin_channel) if reaction.channel_id != admin_channel) {
+                    continue;
+                }
+
+                if !admins.allows(reaction.user_id, &member_roles(&state, reaction.channel_id, reaction.user_id)) {
+                    continue;
+                }
+
+                let issuer = Issuer { name: member_name(&state, reaction.channel_id, reaction.user_id), id: reaction.user_id };
+                discord_send.send((reaction.channel_id, Some(issuer), FromDiscord::RestartReactionEvent(reaction.message_id)))?;
             },
             _ => ()
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("18:30"), Ok((18, 30)));
+        assert_eq!(parse_time("0:05"), Ok((0, 5)));
+        assert_eq!(parse_time("23:59"), Ok((23, 59)));
+    }
+
+    #[test]
+    fn test_parse_time_invalid() {
+        assert!(parse_time("25:00").is_err());
+        assert!(parse_time("12:75").is_err());
+        assert!(parse_time("abc").is_err());
+        assert!(parse_time("").is_err());
+        assert!(parse_time("-1:00").is_err());
+        assert!(parse_time("12:00:00").is_err());
+    }
+
+    #[test]
+    fn test_admins() {
+        let everyone = Admins::from_config(&Config::from_value(&serde_json::json!({})).unwrap());
+        assert!(everyone.allows(UserId(1), &[]));
+
+        let admins = Admins::from_config(&Config::from_value(&serde_json::json!({ "owner_id": 1, "admin_users": [2], "admin_roles": [10] })).unwrap());
+        assert!(admins.allows(UserId(1), &[]));
+        assert!(admins.allows(UserId(2), &[]));
+        assert!(admins.allows(UserId(3), &[RoleId(11), RoleId(10)]));
+        assert!(!admins.allows(UserId(3), &[RoleId(11)]));
+    }
+
+    #[test]
+    fn test_listening_follows_reload() {
+        let config: SharedConfig = std::sync::Arc::new(std::sync::RwLock::new(Config::default()));
+        let listening = Listening::from_config(&config.read().unwrap(), ChannelId(1));
+        assert_eq!(listening.channels, vec![ChannelId(1)]);
+        assert_eq!(listening.relay_channel, ChannelId(1));
+        assert!(listening.admins.allows(UserId(3), &[]));
+
+        *config.write().unwrap() = Config::from_value(&serde_json::json!({
+            "prefix": "!", "channels": [2], "admin_channel": 3, "admin_users": [4], "relay": { "channel": 5 }
+        })).unwrap();
+        let listening = Listening::from_config(&config.read().unwrap(), ChannelId(1));
+        assert_eq!(listening.prefix, "!");
+        assert_eq!(listening.channels, vec![ChannelId(2), ChannelId(3)]);
+        assert_eq!(listening.relay_channel, ChannelId(5));
+        assert!(!listening.admins.allows(UserId(3), &[]));
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use discord::Discord;
+use discord::model::{ChannelId, MessageId, ReactionEmoji};
+use log::*;
+
+use crate::discord_commands::{FromDiscord, Issuer};
+use crate::rate_limit::RateLimiter;
+
+/// A message for the sender thread, sent in the order they were queued.
+pub enum Outgoing {
+    Message {
+        channel: ChannelId,
+        text: String
+    },
+    /// A message with `emoji` added as a reaction, its ID goes back to the main thread as a `RestartOfferedEvent`.
+    RestartOffer {
+        channel: ChannelId,
+        text: String,
+        emoji: &'static str
+    },
+    /// Edits the last heartbeat message, or sends a new one if it's gone.
+    Heartbeat {
+        channel: ChannelId,
+        text: String
+    },
+}
+
+/// Queues messages for a thread that sends them, so the main loop never blocks on the rate limit.
+/// Dropping it waits for the queue to be sent.
+pub struct DiscordSender {
+    outgoing: Option<Sender<Outgoing>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DiscordSender {
+    pub fn spawn(bot: Arc<Discord>, rate_limiter: RateLimiter, discord_send: Sender<(ChannelId, Option<Issuer>, FromDiscord)>) -> DiscordSender {
+        let (outgoing, queued) = unbounded();
+        let thread = spawn_sender_thread(bot, rate_limiter, queued, discord_send);
+
+        DiscordSender { outgoing: Some(outgoing), thread: Some(thread) }
+    }
+
+    pub fn send(&self, message: Outgoing) {
+        if let Some(Err(_)) = self.outgoing.as_ref().map(|outgoing| outgoing.send(message)) {
+            error!("Failed to queue message, the sender thread is gone!");
+        }
+    }
+
+    pub fn message(&self, channel: ChannelId, text: &str) {
+        self.send(Outgoing::Message { channel, text: text.to_string() });
+    }
+}
+
+impl Drop for DiscordSender {
+    fn drop(&mut self) {
+        self.outgoing.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+fn spawn_sender_thread(bot: Arc<Discord>, mut rate_limiter: RateLimiter, outgoing: Receiver<Outgoing>, discord_send: Sender<(ChannelId, Option<Issuer>, FromDiscord)>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut heartbeat_message: Option<MessageId> = None;
+
+        let send_message = |rate_limiter: &mut RateLimiter, channel: ChannelId, text: &str| {
+            let wait = rate_limiter.acquire(Instant::now());
+            if wait > std::time::Duration::from_secs(0) {
+                debug!("Rate limited, waiting {}ms", wait.as_millis());
+                thread::sleep(wait);
+            }
+
+            bot.send_message(channel, text, "", false)
+        };
+
+        for message in outgoing.iter() {
+            match message {
+                Outgoing::Message { channel, text } => {
+                    if let Err(x) = send_message(&mut rate_limiter, channel, text.as_str()) {
+                        error!("Failed to send message! - {}", x);
+                    }
+                },
+                Outgoing::RestartOffer { channel, text, emoji } => match send_message(&mut rate_limiter, channel, text.as_str()) {
+                    Ok(message) => {
+                        if let Err(x) = bot.add_reaction(message.channel_id, message.id, ReactionEmoji::Unicode(emoji.to_string())) {
+                            error!("Failed to add restart reaction! - {}", x);
+                        }
+                        // Not waited on, the main thread may be gone when shutting down
+                        if discord_send.try_send((channel, None, FromDiscord::RestartOfferedEvent(message.id))).is_err() {
+                            error!("Failed to pass on the restart message!");
+                        }
+                    },
+                    Err(x) => error!("Failed to send message! - {}", x),
+                },
+                Outgoing::Heartbeat { channel, text } => {
+                    let edited = heartbeat_message.map(|id| bot.edit_message(channel, id, text.as_str()).is_ok());
+                    if edited != Some(true) {
+                        match send_message(&mut rate_limiter, channel, text.as_str()) {
+                            Ok(message) => heartbeat_message = Some(message.id),
+                            Err(x) => error!("Failed to send heartbeat message! - {}", x),
+                        }
+                    }
+                },
+            }
+        }
+    })
+}
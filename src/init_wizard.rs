@@ -0,0 +1,140 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, prelude::*};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+fn validate_not_empty(input: &str) -> Result<String, String> {
+    if input.is_empty() {
+        return Err("Can't be empty".to_string());
+    }
+
+    Ok(input.to_string())
+}
+
+/// RAM is passed straight to `-Xms`/`-Xmx`, eg. `512M` or `4G`.
+fn validate_ram(input: &str) -> Result<String, String> {
+    let input = input.to_uppercase();
+    let amount = input.strip_suffix('M').or_else(|| input.strip_suffix('G'));
+
+    match amount.map(str::parse::<u32>) {
+        Some(Ok(amount)) if amount > 0 => Ok(input.clone()),
+        _ => Err("Expected an amount like 512M or 4G".to_string()),
+    }
+}
+
+fn validate_channel_id(input: &str) -> Result<u64, String> {
+    input.parse().map_err(|_| "Expected a numeric channel ID, right click the channel > Copy ID".to_string())
+}
+
+fn validate_file(input: &str) -> Result<String, String> {
+    if !Path::new(input).is_file() {
+        return Err(format!("{} is not a file", input));
+    }
+
+    Ok(input.to_string())
+}
+
+fn validate_folder(input: &str) -> Result<String, String> {
+    if !Path::new(input).is_dir() {
+        return Err(format!("{} is not a folder", input));
+    }
+
+    Ok(input.to_string())
+}
+
+/// Asks until the answer passes validation. An empty answer picks the default, if there is one.
+fn prompt<T>(question: &str, default: Option<&str>, validate: impl Fn(&str) -> Result<T, String>) -> Result<T, Box<dyn Error>> {
+    let stdin = io::stdin();
+
+    loop {
+        match default {
+            Some(default) => print!("{} [{}]: ", question, default),
+            None => print!("{}: ", question),
+        }
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if stdin.lock().read_line(&mut answer)? == 0 {
+            return Err(Box::from("Setup aborted"));
+        }
+
+        let answer = match answer.trim() {
+            "" => default.unwrap_or_default(),
+            answer => answer,
+        };
+
+        match validate(answer) {
+            Ok(value) => return Ok(value),
+            Err(x) => println!("  {}", x),
+        }
+    }
+}
+
+/// Interactively asks for the required settings and writes them to `path`.
+pub fn run_wizard(path: &str) -> Result<(), Box<dyn Error>> {
+    println!("Setting up {}, press Enter to accept the [default].", path);
+
+    if Path::new(path).exists() {
+        let overwrite = prompt(format!("{} already exists, overwrite it? (y/n)", path).as_str(), Some("n"), |x| Ok(x.eq_ignore_ascii_case("y")))?;
+        if !overwrite {
+            return Err(Box::from("Setup cancelled"));
+        }
+    }
+
+    let token = prompt("Discord bot token, from the Bot page of the Discord developer portal", None, validate_not_empty)?;
+    let channel = prompt("Discord channel ID", None, validate_channel_id)?;
+
+    let java_path = prompt("Java path", Some("java"), validate_not_empty)?;
+    let mcrcon_path = prompt("mcrcon path", None, validate_file)?;
+    let server_path = prompt("Server jar path", None, validate_file)?;
+    let server_folder = prompt("Server folder", None, validate_folder)?;
+
+    let min_ram = prompt("Minimum RAM", Some("2G"), validate_ram)?;
+    let max_ram = prompt("Maximum RAM", Some("4G"), validate_ram)?;
+
+    let rcon_password = prompt("RCON password, same as rcon.password in server.properties", None, validate_not_empty)?;
+
+    let config: Value = json!({
+        "token": token,
+        "rcon_password": rcon_password,
+
+        "java-path": java_path,
+        "mcrcon-path": mcrcon_path,
+        "server-path": server_path,
+        "server-folder": server_folder,
+
+        "min-ram": min_ram,
+        "max-ram": max_ram,
+
+        "channel_id": channel,
+    });
+
+    fs::write(path, serde_json::to_string_pretty(&config)? + "\n")?;
+    println!("Wrote {}, see config.json.example for the optional settings.", path);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_ram() {
+        assert_eq!(validate_ram("4G"), Ok("4G".to_string()));
+        assert_eq!(validate_ram("512m"), Ok("512M".to_string()));
+        assert!(validate_ram("4").is_err());
+        assert!(validate_ram("0G").is_err());
+        assert!(validate_ram("G").is_err());
+        assert!(validate_ram("").is_err());
+        assert!(validate_ram("4é").is_err());
+    }
+
+    #[test]
+    fn test_validate_channel_id() {
+        assert_eq!(validate_channel_id("123456789012345678"), Ok(123456789012345678));
+        assert!(validate_channel_id("#general").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::error::Error;
+
+use crate::config::Config;
+
+const LEGACY_FLAGS: [&str; 10] = [
+    "-d64", "-server",
+    "-XX:+AggressiveOpts", "-XX:+UseConcMarkSweepGC",
+    "-XX:+UnlockExperimentalVMOptions", "-XX:+UseParNewGC",
+    "-XX:+ExplicitGCInvokesConcurrent", "-XX:+UseFastAccessorMethods",
+    "-XX:+OptimizeStringConcat", "-XX:+UseAdaptiveGCBoundary",
+];
+
+/// Parses a `-Xmx`-style amount like `512M` or `4G` into megabytes.
+pub fn parse_ram_mb(ram: &str) -> Option<u64> {
+    let ram = ram.trim().to_uppercase();
+
+    if let Some(amount) = ram.strip_suffix('G') {
+        amount.parse::<u64>().ok().map(|x| x * 1024)
+    } else if let Some(amount) = ram.strip_suffix('M') {
+        amount.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Aikar's G1GC flags, see https://mcflags.emc.gs. Heaps over 12GB get larger young generation and regions.
+pub fn aikar_flags(max_ram_mb: u64) -> Vec<String> {
+    let large = max_ram_mb > 12 * 1024;
+    let (new_size, max_new_size, region_size, reserve, occupancy) = if large {
+        (40, 50, "16M", 15, 20)
+    } else {
+        (30, 40, "8M", 20, 15)
+    };
+
+    vec![
+        "-XX:+UseG1GC".to_string(),
+        "-XX:+ParallelRefProcEnabled".to_string(),
+        "-XX:MaxGCPauseMillis=200".to_string(),
+        "-XX:+UnlockExperimentalVMOptions".to_string(),
+        "-XX:+DisableExplicitGC".to_string(),
+        "-XX:+AlwaysPreTouch".to_string(),
+        format!("-XX:G1NewSizePercent={}", new_size),
+        format!("-XX:G1MaxNewSizePercent={}", max_new_size),
+        format!("-XX:G1HeapRegionSize={}", region_size),
+        format!("-XX:G1ReservePercent={}", reserve),
+        "-XX:G1HeapWastePercent=5".to_string(),
+        "-XX:G1MixedGCCountTarget=4".to_string(),
+        format!("-XX:InitiatingHeapOccupancyPercent={}", occupancy),
+        "-XX:G1MixedGCLiveThresholdPercent=90".to_string(),
+        "-XX:G1RSetUpdatingPauseTimePercent=5".to_string(),
+        "-XX:SurvivorRatio=32".to_string(),
+        "-XX:+PerfDisableSharedMem".to_string(),
+        "-XX:MaxTenuringThreshold=1".to_string(),
+        "-Dusing.aikars.flags=https://mcflags.emc.gs".to_string(),
+        "-Daikars.new.flags=true".to_string(),
+    ]
+}
+
+/// The JVM flags for the `jvm_preset` in the config, not including `-Xms`/`-Xmx`.
+/// Without a preset, `jvm_args` replaces the legacy flags if it's set.
+pub fn jvm_flags(config: &Config) -> Result<Vec<String>, Box<dyn Error>> {
+    let default_preset = if config.jvm_args.is_empty() { "legacy" } else { "custom" };
+
+    match config.jvm_preset.as_deref().unwrap_or(default_preset) {
+        "legacy" => Ok(LEGACY_FLAGS.iter().map(|x| x.to_string()).collect()),
+        "aikar" => {
+            if config.max_ram.is_empty() {
+                return Err("No max-ram in config file".into());
+            }
+            let max_ram_mb = parse_ram_mb(&config.max_ram).ok_or_else(|| format!("max-ram ({}) should look like 4G or 4096M", config.max_ram))?;
+
+            Ok(aikar_flags(max_ram_mb))
+        },
+        "custom" if config.jvm_args.is_empty() => Err("jvm_preset custom needs a jvm_args list in config file".into()),
+        "custom" => Ok(config.jvm_args.clone()),
+        preset => Err(format!("Unknown jvm_preset {}, expected aikar, legacy or custom", preset).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(config: serde_json::Value) -> Result<Vec<String>, Box<dyn Error>> {
+        Config::from_value(&config).map(|config| config.jvm_flags)
+    }
+
+    #[test]
+    fn test_parse_ram_mb() {
+        assert_eq!(parse_ram_mb("4G"), Some(4096));
+        assert_eq!(parse_ram_mb("512m"), Some(512));
+        assert_eq!(parse_ram_mb("4GB"), None);
+    }
+
+    #[test]
+    fn test_aikar_flags_scale_with_ram() {
+        let small = flags(serde_json::json!({ "jvm_preset": "aikar", "max-ram": "10G" })).unwrap();
+        assert!(small.contains(&"-XX:G1HeapRegionSize=8M".to_string()));
+        assert!(small.contains(&"-XX:G1NewSizePercent=30".to_string()));
+
+        let large = flags(serde_json::json!({ "jvm_preset": "aikar", "max-ram": "16G" })).unwrap();
+        assert!(large.contains(&"-XX:G1HeapRegionSize=16M".to_string()));
+        assert!(large.contains(&"-XX:InitiatingHeapOccupancyPercent=20".to_string()));
+    }
+
+    #[test]
+    fn test_jvm_presets() {
+        assert_eq!(flags(serde_json::json!({})).unwrap().len(), LEGACY_FLAGS.len());
+        assert_eq!(flags(serde_json::json!({ "jvm_preset": "custom", "jvm_args": ["-XX:+UseZGC"] })).unwrap(), vec!["-XX:+UseZGC"]);
+        assert!(flags(serde_json::json!({ "jvm_preset": "custom" })).is_err());
+        assert!(flags(serde_json::json!({ "jvm_preset": "fast" })).is_err());
+        assert_eq!(flags(serde_json::json!({ "jvm_args": ["-XX:+UseG1GC"] })).unwrap(), vec!["-XX:+UseG1GC"]);
+        assert_eq!(flags(serde_json::json!({ "jvm_args": [] })).unwrap().len(), LEGACY_FLAGS.len());
+    }
+}
@@ -1,39 +1,61 @@
+use std::collections::HashMap;
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
 use std::thread;
 use std::process::{Command, Child, Stdio};
+use std::sync::{Arc, PoisonError, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
 
 use chrono::{DateTime, Duration, Local};
-use crossbeam::channel::{bounded, select, tick};
+use crossbeam::channel::{bounded, select, tick, Receiver, Sender};
 use discord::{Discord, State};
-use discord::model::{ChannelId};
+use discord::model::{ChannelId, MessageId};
 use log::*;
-use serde_json::Value;
 
+mod bot_state;
+mod config;
 mod discord_commands;
+mod discord_sender;
+mod init_wizard;
+mod jvm_flags;
+mod message_format;
+mod metrics;
+mod player_stats;
+mod preflight;
+mod secrets;
+mod playtime;
+mod rate_limit;
 mod server_log;
+mod server_properties;
+mod server_state;
+mod system_info;
+mod tps;
 
-use server_log::{FromServerLog, server_log_thread};
-use discord_commands::{FromDiscord, discord_thread};
-
-// KIVANITT => #mc-server
-const BOT_CHANNEL: u64 = include!("../server_id.txt");
-const PREFIX: &'static str = "mc!";
+use server_log::{FromServerLog, LogTail, server_log_thread};
+use bot_state::BotState;
+use config::{Config, SharedConfig};
+use discord_commands::{FromDiscord, Issuer, RESTART_EMOJI, discord_thread};
+use discord_sender::{DiscordSender, Outgoing};
+use player_stats::{PlayerList, PlayerStats};
+use rate_limit::RateLimiter;
+use server_state::ServerState;
+use tps::TpsWindow;
+use playtime::Playtime;
 
 static CONSOLE_ENABLED: AtomicBool = AtomicBool::new(false);
 
-macro_rules! get_option {
-    ($config:expr, $name:literal) => {
-        $config.get($name).and_then(Value::as_str).ok_or(format!("No {} in config file", $name))?
-    };
-}
+const TARGET_LOG_FILES: [(&str, &str); 3] = [
+    ("server_chat", "chat.log"),
+    ("server_log", "server.log"),
+    ("server_status", "status.log"),
+];
 
-fn setup_logger(config: &Value) -> Result<(), Box<dyn Error>> { 
-    let config_level = log::LevelFilter::Info;
+fn setup_logger(config: &Config) -> Result<(), Box<dyn Error>> { 
+    let config_level = config.log_level();
 
-    fern::Dispatch::new()
+    let mut dispatch = fern::Dispatch::new()
         .format(|out, message, record| {
             out.finish(format_args!(
                 "{}[{}][{}] {}",
@@ -44,24 +66,158 @@ fn setup_logger(config: &Value) -> Result<(), Box<dyn Error>> {
             ))
         })
         .level(config_level)
-        .chain(std::io::stdout())
-        .chain(fern::log_file("output.log")?)
-        .apply()?;
+        .chain(std::io::stdout());
+
+    match fern::log_file("output.log") {
+        Ok(log_file) => dispatch = dispatch.chain(log_file),
+        Err(x) => eprintln!("Could not open output.log, logging to stdout only - {}", x),
+    }
+
+    // The server's own output also goes to separate files, to tail just the chat or the server log
+    for (target, path) in TARGET_LOG_FILES.iter() {
+        match fern::log_file(path) {
+            Ok(log_file) => dispatch = dispatch.chain(fern::Dispatch::new()
+                .filter(move |metadata| metadata.target() == *target)
+                .chain(log_file)),
+            Err(x) => eprintln!("Could not open {}, {} is only logged to output.log - {}", path, target, x),
+        }
+    }
 
-    let console_enabled = config.get("console_enabled").map(|x| x.as_bool().unwrap_or_default()).unwrap_or_default();
-    CONSOLE_ENABLED.store(console_enabled, Ordering::Relaxed);
+    dispatch.apply()?;
+
+    CONSOLE_ENABLED.store(config.console_enabled, Ordering::Relaxed);
 
     Ok(())
 }
 
-fn create_discord_client(config: &Value) -> Result<Discord, Box<dyn Error>> {
-    let username = get_option!(config, "username");
-    let password = get_option!(config, "password");
+fn create_discord_client_from_token(token: &str) -> Result<Discord, Box<dyn Error>> {
+    Ok(Discord::from_bot_token(token)?)
+}
+
+/// Logs in with the bot token, or the legacy username and password if there's no token.
+fn create_discord_client(config: &Config) -> Result<Discord, Box<dyn Error>> {
+    if let Some(token) = config.token.as_deref().filter(|x| !x.is_empty()) {
+        return create_discord_client_from_token(token);
+    }
+
+    let username = config.username.as_deref().ok_or("No username in config file")?;
+    let password = config.password.as_deref().ok_or("No password in config file")?;
+    warn!("Logging in with username and password, which Discord has disabled for most accounts. Set token in the config file instead.");
 
     #[allow(deprecated)]
     return Ok(Discord::new(username, password)?);
 }
 
+/// The channel the bot reports to, which also takes commands if no `channels` are set.
+fn bot_channel(config: &Config) -> Result<ChannelId, Box<dyn Error>> {
+    Some(config.channel_id).filter(|&x| x != 0)
+        .map(ChannelId)
+        .ok_or_else(|| "channel_id in config file must be a numeric channel ID".into())
+}
+
+fn load_config(path: &str) -> Result<Config, Box<dyn Error>> {
+    let mut file = File::open(path)?;
+    let mut config_str = String::new();
+    file.read_to_string(&mut config_str)?;
+
+    let mut config = serde_json::from_str(config_str.as_str())?;
+    secrets::resolve_secrets(&mut config)?;
+
+    Config::from_value(&config)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let hours = duration.num_hours();
+    let minutes = duration.num_minutes() % 60;
+
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// The next time the clock shows `hour:minute`, today if it's still ahead, tomorrow otherwise.
+fn next_occurrence(now: DateTime<Local>, hour: u8, minute: u8) -> Option<DateTime<Local>> {
+    let today = now.date().and_hms_opt(hour.into(), minute.into(), 0)?;
+    if today > now {
+        return Some(today);
+    }
+
+    (now.date() + Duration::days(1)).and_hms_opt(hour.into(), minute.into(), 0)
+}
+
+/// Takes the scheduled shutdown once its time has come.
+fn shutdown_due(scheduled: &mut Option<DateTime<Local>>, now: DateTime<Local>) -> bool {
+    match scheduled {
+        Some(time) if *time <= now => {
+            *scheduled = None;
+            true
+        },
+        _ => false
+    }
+}
+
+/// Why a scheduled shutdown that came due can't stop the server, `None` if it can.
+fn shutdown_skip_reason(server_status: &ServerStatus) -> Option<&'static str> {
+    match server_status {
+        ServerStatus::Running{..} => None,
+        ServerStatus::Stopping{..} => Some("the server is already stopping"),
+        ServerStatus::Monitoring{..} => Some("the server wasn't started by the bot"),
+        _ => Some("the server isn't running"),
+    }
+}
+
+/// Drops a pending scheduled shutdown and its warnings, with the reply for whoever cancelled it.
+fn cancel_scheduled_shutdown(scheduled: &mut Option<DateTime<Local>>, warned: &mut Vec<Duration>, user: &str) -> Option<String> {
+    warned.clear();
+    scheduled.take().map(|time| format!("Shutdown at {} cancelled by {}", time.format("%H:%M"), user))
+}
+
+/// The restart warning that's due for the scheduled shutdown, remembered in `warned` so it only goes out once.
+/// Warnings that came due together, like after scheduling a shutdown close to now, go out as just the closest one.
+fn restart_warning_due(scheduled: Option<DateTime<Local>>, offsets: &[Duration], warned: &mut Vec<Duration>, now: DateTime<Local>) -> Option<Duration> {
+    let remaining = scheduled? - now;
+    let due: Vec<Duration> = offsets.iter().copied()
+        .filter(|offset| remaining <= *offset && !warned.contains(offset))
+        .collect();
+
+    warned.extend(due.iter().copied());
+    due.into_iter().min()
+}
+
+/// Whether `pid` still runs the server jar, in case the PID was reused since it was saved.
+fn is_server_process(config: &Config, pid: u32) -> bool {
+    let server_path = if config.server_path.is_empty() { "java" } else { config.server_path.as_str() };
+    match system_info::process_command_line(pid) {
+        Some(cmdline) => cmdline.contains(server_path),
+        // Without a command line to check, any live process with the PID counts as the server
+        None => system_info::process_alive(pid).unwrap_or(true),
+    }
+}
+
+/// Who stopped the server, so the Discord messages can say why it's going down.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum StopInitiator {
+    Admin,
+    Console,
+    Scheduled,
+    BotExit,
+    Restart,
+}
+
+impl StopInitiator {
+    fn describe(&self) -> &'static str {
+        match self {
+            StopInitiator::Admin => "requested from Discord",
+            StopInitiator::Console => "triggered from console",
+            StopInitiator::Scheduled => "scheduled shutdown",
+            StopInitiator::BotExit => "bot shutting down",
+            StopInitiator::Restart => "restarting",
+        }
+    }
+}
+
 enum ServerStatus {
     Unknown,
     Offline,
@@ -71,80 +227,627 @@ enum ServerStatus {
     },
     Running {
         server: Child,
+        start_time: DateTime<Local>
     },
     Stopping {
         server: Option<Child>,
+        start_time: Option<DateTime<Local>>,
         rcon: Option<Child>,
+        initiator: StopInitiator,
+    },
+    /// Someone else runs the server, with `managed` off. Only its log is followed.
+    Monitoring {
+        up: bool
+    }
+}
+
+impl ServerStatus {
+    /// The server process, while we own it.
+    fn pid(&self) -> Option<u32> {
+        match self {
+            ServerStatus::Starting{ server, .. }
+            | ServerStatus::Running{ server, .. }
+            | ServerStatus::Stopping{ server: Some(server), .. } => Some(server.id()),
+            _ => None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ServerStatus::Unknown => "unknown",
+            ServerStatus::Offline => "offline",
+            ServerStatus::Starting{..} => "starting",
+            ServerStatus::Running{..} => "running",
+            ServerStatus::Stopping{..} => "stopping",
+            ServerStatus::Monitoring{ up: true } => "running",
+            ServerStatus::Monitoring{ up: false } => "offline",
+        }
+    }
+
+    /// Whether the server is up and taking commands, whether or not we own it.
+    fn is_up(&self) -> bool {
+        matches!(self, ServerStatus::Running{..} | ServerStatus::Monitoring{ up: true })
+    }
+}
+
+/// Connects to Discord and spawns the thread receiving its events.
+/// If the thread fails, it asks the main thread to reconnect instead of dying silently.
+fn spawn_discord_thread(config: &SharedConfig, bot: &Discord, bot_channel: ChannelId, presence: &Receiver<&'static str>, discord_send: Sender<(ChannelId, Option<Issuer>, FromDiscord)>) -> Result<(), Box<dyn Error>> {
+    let config = config.clone();
+    let presence = presence.clone();
+    let error_send = discord_send.clone();
+
+    let (connection, ready) = bot.connect()?;
+    let state = State::new(ready);
+
+    thread::spawn(move || {
+        if let Err(x) = discord_thread(config, connection, state, bot_channel, presence, discord_send) {
+            error!("Discord thread failed, reconnecting! - {}", x);
+            error_send.send((bot_channel, None, FromDiscord::ReconnectEvent)).ok();
+        }
+    });
+
+    Ok(())
+}
+
+/// The log file to follow when `log_source` isn't `pipe`, relative to the server folder.
+/// With the default `pipe`, the server's stdout is read instead.
+fn log_source_file(config: &Config) -> Option<PathBuf> {
+    match config.log_source.as_deref() {
+        None | Some("") | Some("pipe") => None,
+        Some(path) => Some(Path::new(&config.server_folder).join(path)),
+    }
+}
+
+/// The log file to follow, the `log_source` or the server's `logs/latest.log`.
+fn server_log_file(config: &Config) -> PathBuf {
+    log_source_file(config).unwrap_or_else(|| Path::new(&config.server_folder).join("logs").join("latest.log"))
+}
+
+/// Spawns the thread scanning the server log, which reports `PipeClosed` when the log ends.
+fn spawn_server_log_thread(config: &Config, output: impl Read + Send + 'static, log_send: Sender<FromServerLog>) {
+    let config = config.clone();
+
+    thread::spawn(move || {
+        if let Err(x) = server_log_thread(config, output, log_send.clone(), false) {
+            error!("Server log thread failed! - {}", x);
+        }
+        log_send.send(FromServerLog::PipeClosed).ok();
+    });
+}
+
+/// Builds a command run through the system shell, for user-provided command templates.
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(&["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(&["-c", command]);
+        shell
+    }
+}
+
+/// Quotes an argument so `shell_command` passes it through as a single word.
+fn shell_quote(arg: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn newest_file(folder: &Path) -> Option<PathBuf> {
+    fs::read_dir(folder).ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().map(|x| x.is_file()).unwrap_or(false))
+        .max_by_key(|entry| entry.metadata().and_then(|x| x.modified()).ok())
+        .map(|entry| entry.path())
+}
+
+/// mcrcon with the connection flags from the config, ready for commands.
+fn mcrcon(config: &Config) -> Command {
+    let mut command = Command::new(&config.mcrcon_path);
+    command.args(&["-P", config.rcon_port.to_string().as_str(), "-p", config.rcon_password.as_str()]);
+
+    command
+}
+
+/// A typo in mcrcon-path shouldn't look like the server refusing the command.
+fn rcon_launch_error(config: &Config, x: std::io::Error) -> Box<dyn Error> {
+    format!("RCON tool not found or failed to launch ({}) - {}", config.mcrcon_path, x).into()
+}
+
+/// What mcrcon said when it failed, eg. `Connection failed. Error 111: Connection refused.`
+fn rcon_error_message(output: &str, exit_status: impl std::fmt::Display) -> String {
+    let output = output.split_whitespace().collect::<Vec<_>>().join(" ");
+    if output.is_empty() {
+        format!("mcrcon exited with {}", exit_status)
+    } else {
+        output
+    }
+}
+
+/// Starts mcrcon silently with the given commands, which may begin with mcrcon flags like `-w`.
+/// Its output is only errors, see `rcon_failure`.
+fn run_rcon(config: &Config, commands: &[&str]) -> Result<Child, Box<dyn Error>> {
+    mcrcon(config)
+        .arg("-s")
+        .args(commands)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|x| rcon_launch_error(config, x))
+}
+
+/// Why a process from `run_rcon` failed, once it has exited unsuccessfully.
+fn rcon_failure(rcon: &mut Child) -> Option<String> {
+    match rcon.try_wait() {
+        Ok(Some(exit_status)) if !exit_status.success() => {
+            let mut output = String::new();
+            if let Some(stdout) = rcon.stdout.as_mut() {
+                stdout.read_to_string(&mut output).ok();
+            }
+            Some(rcon_error_message(output.as_str(), exit_status))
+        },
+        _ => None
+    }
+}
+
+/// Runs RCON commands and waits for their output.
+fn rcon_output(config: &Config, commands: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = mcrcon(config)
+        .arg("-c")
+        .args(commands)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|x| rcon_launch_error(config, x))?;
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stdout) + String::from_utf8_lossy(&output.stderr);
+        return Err(rcon_error_message(&message, output.status).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs an RCON command on its own thread, for messages nobody waits on.
+fn rcon_in_background(config: &Config, command: String) {
+    let config = config.clone();
+
+    thread::spawn(move || {
+        if let Err(x) = rcon_output(&config, &[command.as_str()]) {
+            error!("Failed to run `{}` through RCON! - {}", command, x);
+        }
+    });
+}
+
+/// Whether a player can be greeted again, so reconnecting doesn't spam the chat.
+fn greeting_allowed(last_greeted: &mut HashMap<String, DateTime<Local>>, name: &str, now: DateTime<Local>, cooldown: Duration) -> bool {
+    match last_greeted.get(name) {
+        Some(last) if now - *last < cooldown => false,
+        _ => {
+            last_greeted.insert(name.to_string(), now);
+            true
+        }
+    }
+}
+
+/// Handles the server logging that it's stopping.
+/// A stop we started ourselves is already `Stopping` with its RCON process, which is kept.
+/// Otherwise someone stopped it from the console.
+fn on_server_stopping(server_status: ServerStatus) -> ServerStatus {
+    match server_status {
+        ServerStatus::Running { server, start_time } => ServerStatus::Stopping {
+            server: Some(server),
+            start_time: Some(start_time),
+            rcon: None,
+            initiator: StopInitiator::Console
+        },
+        other => other
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum CancelOutcome {
+    Cancelled,
+    TooLate,
+    Failed(String),
+    NotCancellable,
+    NotRunning,
+    NothingToCancel,
+}
+
+/// Cancels an admin stop by killing its RCON countdown.
+/// If the server or the countdown already finished, it's too late and the status is left for the main loop to clean up.
+fn cancel_stop(server_status: ServerStatus) -> (ServerStatus, CancelOutcome) {
+    match server_status {
+        ServerStatus::Stopping{ mut server, start_time, rcon: Some(mut rcon), initiator } => {
+            let server_stopped = server.as_mut().map(|server| !matches!(server.try_wait(), Ok(None))).unwrap_or(false);
+            let rcon_finished = !matches!(rcon.try_wait(), Ok(None));
+
+            if server_stopped || rcon_finished {
+                return (ServerStatus::Stopping{ server, start_time, rcon: Some(rcon), initiator }, CancelOutcome::TooLate);
+            }
+
+            if let Err(x) = rcon.kill() {
+                return (ServerStatus::Stopping{ server, start_time, rcon: Some(rcon), initiator }, CancelOutcome::Failed(x.to_string()));
+            }
+            rcon.wait().ok();
+
+            match server {
+                Some(server) => (ServerStatus::Running{ server, start_time: start_time.unwrap_or_else(Local::now) }, CancelOutcome::Cancelled),
+                None => (ServerStatus::Unknown, CancelOutcome::Cancelled),
+            }
+        },
+        status @ ServerStatus::Stopping{ rcon: None, .. } => (status, CancelOutcome::NotCancellable),
+        ServerStatus::Offline => (ServerStatus::Offline, CancelOutcome::NotRunning),
+        status => (status, CancelOutcome::NothingToCancel),
+    }
+}
+
+/// Rejects commands that need the server up, with the message to send back.
+/// An unknown status is given a try, the server may have been running before the bot started.
+fn require_running(server_status: &ServerStatus) -> Result<(), &'static str> {
+    match server_status {
+        ServerStatus::Offline
+        | ServerStatus::Starting{..}
+        | ServerStatus::Monitoring{ up: false } => Err("Server's not running (yet)"),
+        ServerStatus::Stopping{..} => Err("Server's stopping"),
+        _ => Ok(())
+    }
+}
+
+fn check_backup_allowed(server_status: &ServerStatus, backup_in_progress: bool) -> Result<(), &'static str> {
+    require_running(server_status)?;
+
+    if backup_in_progress {
+        return Err("A backup is already running");
     }
+
+    Ok(())
 }
 
-fn main_thread(config: &Value, bot: Discord) -> Result<(), Box<dyn Error>> {
+fn main_thread(config_path: &str, config: &Config, bot: Discord, bot_channel: ChannelId) -> Result<(), Box<dyn Error>> {
+    let bot = Arc::new(bot);
     #[allow(non_snake_case)] let ERROR_TIMEOUT: Duration = Duration::seconds(15);
     #[allow(non_snake_case)] let MESSAGE_TIMEOUT: Duration = Duration::seconds(2);
+    const BACKUP_HISTORY_LEN: usize = 10;
 
     let mut server_status = ServerStatus::Unknown;
     let mut last_error_reported = Local::now();
+    let mut startup_error: Option<String> = None;
+    // The last thing the server said on stderr, for when it exits during startup without an error
+    let mut startup_output: Option<String> = None;
+    let mut startup_warned = false;
+    let mut restart_warned: Vec<Duration> = Vec::new();
+    let mut stop_announced = false;
+    let mut stop_logged = false;
+    let mut exit_requested = false;
+    let mut restart_pending: Option<ChannelId> = None;
     let mut last_chat_msg = Local::now();
-    
-    struct CachedChat { name: String, message: String };
-    let mut chat_msg_cache = Vec::<CachedChat>::new();
+    let mut backup_in_progress = false;
+    let mut backup_sync: Option<Child> = None;
+    let mut update_process: Option<Child> = None;
+    let mut restart_message: Option<MessageId> = None;
+    let mut last_heartbeat = Local::now();
+    let mut log_tail: Option<Arc<AtomicBool>> = None;
 
-    let (mut from_discord, _discord_handle) = {
-        let config = config.clone();
-        let (discord_send, from_discord) = bounded(5);
-        
-        let (connection, ready) = bot.connect()?;
-        let state = State::new(ready);
+    let mut config = config.clone();
+
+    let mut last_lag_reported = Local::now() - config.lag_report_window();
+    let mut lag_spike_cache = Vec::<Duration>::new();
+    let mut error_cache = Vec::<String>::new();
 
-        let discord_thread = thread::spawn(move || {
-            discord_thread(config, connection, state, discord_send).unwrap();
-        });
+    let mut tps_window = TpsWindow::new(config.tps_alert_window());
+    let mut last_tps_poll = Local::now();
+    let mut tps_poll_running = false;
+    let mut tps_warned = false;
+    let (tps_send, from_tps) = bounded::<Option<f64>>(1);
 
-        (from_discord, discord_thread)
+    let mut last_welcomed = HashMap::<String, DateTime<Local>>::new();
+    let mut last_goodbye = HashMap::<String, DateTime<Local>>::new();
+
+    let stats_path = config.stats_file.clone();
+    let mut empty_since: Option<DateTime<Local>> = None;
+    let mut empty_reminder_sent = false;
+    let mut player_stats = PlayerStats::load(&stats_path);
+
+    let state_path = config.state_file.clone();
+    let mut bot_state = BotState::load(&state_path);
+
+    // A Child can't be rebuilt from a PID, so a server left running by a previous bot run can't be managed,
+    // only kept track of until it exits. RCON still works on it.
+    let mut orphan_pid = match (bot_state.server_status.as_deref(), bot_state.server_pid) {
+        (Some("offline"), _) | (_, None) => None,
+        (Some(status), Some(pid)) if is_server_process(&config, pid) => {
+            warn!("Server was {} when the bot stopped, it's still running (pid {})", status, pid);
+            Some(pid)
+        },
+        (_, Some(pid)) => {
+            info!("Server (pid {}) exited while the bot was down", pid);
+            None
+        }
     };
 
+    // Checking may spawn a process outside Linux, so it's not done on every event
+    let mut orphan_seen = Local::now();
+
+    let playtime_path = config.playtime_file.clone();
+    let mut playtime = Playtime::load(&playtime_path);
+    
+    let server_state = ServerState::shared(clap::crate_version!());
+    // Only held for the statement it's used in, the metrics endpoint reads it too
+    macro_rules! state {
+        () => {
+            server_state.write().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+    // Status changes all go through here, so the state always has the current one
+    macro_rules! set_status {
+        ($status:expr) => {{
+            server_status = $status;
+            let mut state = state!();
+            state.status = server_status.name();
+            state.running_since = match &server_status {
+                ServerStatus::Running{ start_time, .. } => Some(*start_time),
+                _ => None
+            };
+        }}
+    }
+    state!().status = server_status.name();
+    if config.metrics_enabled {
+        let port = config.metrics_port;
+        let address = config.metrics_address.as_str();
+
+        if let Err(x) = metrics::spawn_metrics_server(format!("{}:{}", address, port).as_str(), server_state.clone()) {
+            error!("Failed to start the metrics server! - {}", x);
+        }
+    }
+
+    // Joins and leaves are kept apart from chat, so a burst of them can go out as one line
+    enum CachedChat {
+        Message { name: String, message: String },
+        Join(String),
+        Leave(String),
+    }
+    let mut chat_msg_cache = Vec::<CachedChat>::new();
+
+    // Kept around so the main thread can queue commands for itself, like the start after a restart
+    let (discord_send, from_discord) = bounded::<(ChannelId, Option<Issuer>, FromDiscord)>(5);
+    // Status changes for the bot's presence, pushed to whichever Discord thread is connected
+    let (presence_send, presence_recv) = bounded::<&'static str>(5);
+    let mut presence_sent: Option<&'static str> = None;
+    let shared_config: SharedConfig = Arc::new(RwLock::new(config.clone()));
+    spawn_discord_thread(&shared_config, &bot, bot_channel, &presence_recv, discord_send.clone())?;
+
     let (mut server_log_send, mut from_server_log) = bounded::<FromServerLog>(5);
 
     let timeout = tick(Duration::seconds(1).to_std().unwrap());
-    
-    if let Err(x) = bot.send_message(
-        ChannelId(BOT_CHANNEL), 
-        format!("Server maintainer started, ver {}", clap::crate_version!()).as_str(), 
-        "", false
-    ) {
-        error!("Failed to send message! - {}", x);
+
+    // Without a process to own, the log is all there is to go on
+    if !config.managed {
+        let log_file = server_log_file(&config);
+        let active = Arc::new(AtomicBool::new(true));
+        let tail = LogTail::open(&log_file, active.clone()).map_err(|x| format!("Could not open {} - {}", log_file.display(), x))?;
+        spawn_server_log_thread(&config, tail, server_log_send.clone());
+        log_tail = Some(active);
+
+        set_status!(ServerStatus::Monitoring{ up: rcon_output(&config, &["list"]).is_ok() });
+        info!("Monitoring an external server through {}.", log_file.display());
+    }
+
+    let (signal_send, from_signal) = bounded::<()>(2);
+    ctrlc::set_handler(move || {
+        signal_send.try_send(()).ok();
+    })?;
+
+    // discord-rs waits out Discord's own rate limit responses, this keeps bursts from running into them
+    let rate_limiter = RateLimiter::new(
+        config.rate_limit_messages,
+        std::time::Duration::from_secs(config.rate_limit_seconds),
+        std::time::Instant::now()
+    );
+    let sender = DiscordSender::spawn(bot.clone(), rate_limiter, discord_send.clone());
+    let send_message = |channel: ChannelId, msg: &str| sender.message(channel, msg);
+
+    send_message(bot_channel, format!("Server maintainer started, ver {}", clap::crate_version!()).as_str());
+
+    if let Some(pid) = orphan_pid {
+        let message_str = message_format::warning(format!("A server may already be running from before the bot restarted (pid {}), \
+            it can be stopped with `{prefix}kill` but can't be watched", pid, prefix = config.prefix).as_str());
+        send_message(bot_channel, message_str.as_str());
+    }
+
+    let problems = preflight::preflight_check(&config);
+    if !problems.is_empty() {
+        for problem in problems.iter() {
+            warn!("Preflight check: {}", problem);
+        }
+
+        let message_str = message_format::error("Config problems found, fix them before starting the server", problems.join("\n").as_str());
+        send_message(bot_channel, message_str.as_str());
+
+        if config.preflight_strict {
+            return Err(Box::from("Preflight check failed"));
+        }
     }
 
     loop {
-        let send_discord = |msg: String| {
-            if let Err(_) = bot.send_message(ChannelId(BOT_CHANNEL), msg.as_str(), "", false) {
-                error!("Failed to send message!");
+        let send_discord = |msg: String| send_message(bot_channel, msg.as_str());
+
+        // Retried on the next pass if the Discord thread is behind
+        let status = state!().status;
+        if config.presence && presence_sent != Some(status) && presence_send.try_send(status).is_ok() {
+            presence_sent = Some(status);
+        }
+
+        if let Some(pid) = orphan_pid.filter(|_| Local::now() - orphan_seen >= Duration::seconds(5)) {
+            if is_server_process(&config, pid) {
+                orphan_seen = Local::now();
+            } else {
+                orphan_pid = None;
+
+                state!().online_players.clear();
+                empty_since = None;
+                // Nobody played past the last time the server was seen running
+                playtime.logout_all(orphan_seen);
+                if let Err(x) = playtime.save(&playtime_path) {
+                    error!("Failed to save playtime! - {}", x);
+                }
+
+                if matches!(server_status, ServerStatus::Unknown | ServerStatus::Stopping{ server: None, .. }) {
+                    set_status!(ServerStatus::Offline);
+                }
+                send_discord(message_format::status("Server from before the bot restarted has stopped."));
+                info!("Orphaned server (pid {}) exited.", pid);
             }
-        };
+        }
+
+        // Saved on every transition, so the next bot run knows about a server that's still up
+        let saved_pid = server_status.pid().or(orphan_pid);
+        if bot_state.server_status.as_deref() != Some(server_status.name()) || bot_state.server_pid != saved_pid {
+            if bot_state.server_status.as_deref() != Some(server_status.name()) {
+                info!(target: "server_status", "{} -> {}", bot_state.server_status.as_deref().unwrap_or("unknown"), server_status.name());
+            }
+            bot_state.server_status = Some(server_status.name().to_string());
+            bot_state.server_pid = saved_pid;
+            if let Err(x) = bot_state.save(&state_path) {
+                error!("Failed to save bot state! - {}", x);
+            }
+        }
+
+        // Sends the queued chat lines in one message
+        macro_rules! flush_chat {
+            ($now:expr) => {
+                let mut message_str = String::new();
+                let mut cached = chat_msg_cache.iter().peekable();
+
+                while let Some(entry) = cached.next() {
+                    let line = match entry {
+                        CachedChat::Message { name, message } => message_format::chat_line(name, message),
+                        CachedChat::Join(name) | CachedChat::Leave(name) => {
+                            let mut names = vec![name.clone()];
+                            while let Some(CachedChat::Join(next) | CachedChat::Leave(next)) = cached.next_if(|next| std::mem::discriminant(*next) == std::mem::discriminant(entry)) {
+                                names.push(next.clone());
+                            }
+
+                            let template = if let CachedChat::Join(_) = entry { &config.join_message } else { &config.leave_message };
+                            let players = message_format::name_list(&names, 2);
+                            message_format::chat_line("Server", message_format::minecraft_formatting(&template.replace("{player}", &players), config.color_codes_markdown()))
+                        }
+                    };
+                    message_str += format!("\n{}", line).as_str();
+                }
+
+                send_message(config.relay.channel.map(ChannelId).unwrap_or(bot_channel), message_str.as_str());
+
+                last_chat_msg = $now;
+                chat_msg_cache.clear();
+            }
+        }
+
+        macro_rules! queue_chat {
+            ($entry:expr, $now:expr) => {
+                let now = $now;
+                chat_msg_cache.push($entry);
+                if now - last_chat_msg > MESSAGE_TIMEOUT {
+                    flush_chat!(now);
+                }
+            }
+        }
 
         macro_rules! send_or_queue {
             ($name:expr, $message:expr) => {
-                let now = Local::now();
-                if now - last_chat_msg > MESSAGE_TIMEOUT {
-                    let mut message_str = String::new();
-    
-                    for CachedChat { name, message } in chat_msg_cache.iter() {
-                        message_str += format!("\n<**{}**> {}", name, message).as_str();
+                send_or_queue!($name, $message, Local::now());
+            };
+            ($name:expr, $message:expr, $time:expr) => {
+                let name = message_format::minecraft_formatting(&$name, config.color_codes_markdown());
+                let message = message_format::minecraft_formatting(&$message, config.color_codes_markdown());
+                queue_chat!(CachedChat::Message { name, message }, $time);
+            }
+        }
+
+        if matches!(server_status, ServerStatus::Offline | ServerStatus::Unknown) {
+            if let Some(active) = log_tail.take() {
+                active.store(false, Ordering::Relaxed);
+            }
+
+            if state!().scheduled_shutdown.take().is_some() {
+                restart_warned.clear();
+                info!("Server stopped, dropped the scheduled shutdown.");
+            }
+        }
+
+        if let Some(sync) = &mut backup_sync {
+            match sync.try_wait() {
+                Ok(None) => (),
+                Ok(Some(exit_status)) if exit_status.success() => {
+                    send_or_queue!("Server", "*Backup synced to remote*");
+                    info!("Backup synced.");
+                    backup_sync = None;
+                },
+                Ok(Some(exit_status)) => {
+                    send_discord(message_format::warning(format!("Backup sync failed - {}", exit_status).as_str()));
+                    error!("Backup sync failed - {}", exit_status);
+                    backup_sync = None;
+                },
+                Err(x) => {
+                    error!("Failed to check backup sync - {}", x);
+                    backup_sync = None;
+                }
+            }
+        }
+
+        if let Some(update) = &mut update_process {
+            let finished = match update.try_wait() {
+                Ok(None) => false,
+                Ok(Some(exit_status)) if exit_status.success() => {
+                    send_discord(message_format::status("Update finished"));
+                    info!("Update finished.");
+                    true
+                },
+                Ok(Some(exit_status)) => {
+                    send_discord(message_format::warning(format!("Update failed - {}", exit_status).as_str()));
+                    error!("Update failed - {}", exit_status);
+                    true
+                },
+                Err(x) => {
+                    error!("Failed to check update - {}", x);
+                    true
+                }
+            };
+
+            if finished {
+                update_process = None;
+
+                if config.update_show_output {
+                    let update_log = fs::read_to_string(&config.update_log).unwrap_or_default();
+                    let lines: Vec<&str> = update_log.lines().collect();
+                    let tail = lines[lines.len().saturating_sub(15)..].join("\n");
+
+                    for chunk in message_format::code_block_chunks(tail.as_str()) {
+                        send_discord(chunk);
                     }
-                    
-                    message_str += format!("\n<**{}**> {}", $name, $message).as_str();
-                    send_discord(message_str);
-    
-                    last_chat_msg = now;
-                    chat_msg_cache.clear();
-                } else {
-                    chat_msg_cache.push(CachedChat { name: $name, message: $message });
                 }
             }
         }
 
-        if let ServerStatus::Stopping{ rcon, server } = &mut server_status {
-            if let Some(server) = server {
+        let mut stop_failed = false;
+        if let ServerStatus::Stopping{ rcon, server, initiator, .. } = &mut server_status {
+            let server_alive = match server {
+                Some(server) => matches!(server.try_wait(), Ok(None)),
+                None => true
+            };
+
+            // mcrcon couldn't reach the server, so the stop never went through.
+            // Once the server says it's stopping, losing the connection is expected.
+            if let Some(x) = rcon.as_mut().and_then(rcon_failure).filter(|_| server_alive && !stop_logged) {
+                send_discord(message_format::error("Stop command failed, the server is still up", x.as_str()));
+                error!("Stop command failed! - {}", x);
+                stop_failed = true;
+            } else if let Some(server) = server {
                 let server_stopped = match server.try_wait() {
                     Ok(None) => false,
                     _ => true
@@ -161,36 +864,122 @@ fn main_thread(config: &Value, bot: Discord) -> Result<(), Box<dyn Error>> {
                 };
 
                 if server_stopped {
-                    if !rcon_stopped {
+                    if stop_announced {
+                        stop_announced = false;
+                    } else if !rcon_stopped {
                         if let Some(rcon) = rcon {
                             rcon.kill().ok();
                         }
 
-                        send_discord("Server stopped before time.".to_string());
+                        send_discord(message_format::status("Server stopped before time."));
                         warn!("Server stopped before time.");
                     } else {
-                        send_discord("Server stopped.".to_string());
-                        info!("Server stopped.");
+                        send_discord(message_format::status(format!("Server stopped ({}).", initiator.describe()).as_str()));
+                        info!("Server stopped ({}).", initiator.describe());
+                    }
+
+                    state!().online_players.clear();
+                    empty_since = None;
+                    playtime.logout_all(Local::now());
+                    if let Err(x) = playtime.save(&playtime_path) {
+                        error!("Failed to save playtime! - {}", x);
                     }
 
-                    server_status = ServerStatus::Offline;
+                    backup_in_progress = false;
+                    set_status!(ServerStatus::Offline);
+
+                    if let Some(channel) = restart_pending.take() {
+                        if !exit_requested && discord_send.try_send((channel, None, FromDiscord::StartServerEvent)).is_err() {
+                            send_discord(message_format::status(format!("Couldn't restart the server, `{prefix}start` to start it", prefix = config.prefix).as_str()));
+                            error!("Failed to queue the restart!");
+                        }
+                    }
                 }
             }
-        } else if let ServerStatus::Running{server} = &mut server_status {
+        } else if let ServerStatus::Running{ server, .. } = &mut server_status {
             if match server.try_wait() {
                 Ok(None) => false,
                 _ => true
             } {
-                send_discord(format!("Server died for some reason, {prefix}start to restart", prefix = PREFIX));
-                server_status = ServerStatus::Offline;
+                // Restart reactions are only taken from the admin channel, so that's where the restart offer goes
+                let admin_channel = config.admin_channel.map(ChannelId).unwrap_or(bot_channel);
+                if admin_channel != bot_channel {
+                    send_discord(message_format::status("Server died for some reason."));
+                }
+                let died_message = message_format::status(format!("Server died for some reason, {prefix}start or react with {emoji} to restart",
+                    prefix = config.prefix, emoji = RESTART_EMOJI).as_str());
+                sender.send(Outgoing::RestartOffer { channel: admin_channel, text: died_message, emoji: RESTART_EMOJI });
+                state!().online_players.clear();
+                empty_since = None;
+                playtime.logout_all(Local::now());
+                if let Err(x) = playtime.save(&playtime_path) {
+                    error!("Failed to save playtime! - {}", x);
+                }
+
+                backup_in_progress = false;
+                set_status!(ServerStatus::Offline);
                 error!("Server died!");
             }
+        } else if let ServerStatus::Starting{server, ..} = &mut server_status {
+            let exit_status = match server.try_wait() {
+                Ok(None) => None,
+                Ok(Some(exit_status)) => Some(exit_status.to_string()),
+                Err(x) => Some(x.to_string()),
+            };
+
+            if let Some(exit_status) = exit_status {
+                match startup_error.take().or_else(|| startup_output.take()) {
+                    Some(cause) => send_discord(message_format::error(format!("Server failed to start ({})", exit_status).as_str(), cause.as_str())),
+                    None => send_discord(message_format::status(format!("Server failed to start ({})", exit_status).as_str())),
+                }
+                set_status!(ServerStatus::Offline);
+                error!("Server died during startup! ({})", exit_status);
+            }
+        }
+
+        if stop_failed {
+            restart_pending = None;
+            set_status!(match server_status {
+                ServerStatus::Stopping{ server: Some(server), start_time, .. } => ServerStatus::Running{ server, start_time: start_time.unwrap_or_else(Local::now) },
+                _ => ServerStatus::Unknown,
+            });
+        }
+
+        if exit_requested && matches!(server_status, ServerStatus::Offline | ServerStatus::Unknown | ServerStatus::Monitoring{..}) {
+            send_discord(message_format::status("Bot stopped."));
+            return Ok(());
         }
         
 
         select! {
             recv(from_discord) -> discord_msg => {
-                match discord_msg {
+                // A restart reaction on the latest "Server died" message is a start, reactions on anything else are ignored
+                let discord_msg = match discord_msg {
+                    Ok((channel, issuer, FromDiscord::RestartReactionEvent(message))) if restart_message == Some(message) => {
+                        restart_message = None;
+                        Ok((channel, issuer, FromDiscord::StartServerEvent))
+                    },
+                    other => other
+                };
+                let reply_channel = discord_msg.as_ref().map(|(channel, _, _)| *channel).unwrap_or(bot_channel);
+                let issuer = match &discord_msg {
+                    Ok((_, Some(issuer), event)) => {
+                        info!("{} sent {:?}", issuer, event);
+                        issuer.to_string()
+                    },
+                    _ => "the bot".to_string()
+                };
+                let send_discord = |msg: String| send_message(reply_channel, msg.as_str());
+
+                match discord_msg.map(|(_, _, event)| event) {
+                    Ok(FromDiscord::StartServerEvent)
+                    | Ok(FromDiscord::StopServerEvent)
+                    | Ok(FromDiscord::KillServerEvent)
+                    | Ok(FromDiscord::RestartServerEvent)
+                    | Ok(FromDiscord::ShutdownServerEvent(..)) if !config.managed => {
+                        send_discord("This bot doesn't manage the server".to_string());
+                    },
+
                     Ok(FromDiscord::StartServerEvent) => {
                         match server_status {
                             ServerStatus::Running{..} |
@@ -205,172 +994,315 @@ fn main_thread(config: &Value, bot: Discord) -> Result<(), Box<dyn Error>> {
                             _ => ()
                         }
 
-                        let java_path = get_option!(config, "java-path");
-                        let server_path = get_option!(config, "server-path");
-                        let server_folder = get_option!(config, "server-folder");
-                        let min_ram = format!("-Xms{}", get_option!(config, "min-ram"));
-                        let max_ram = format!("-Xmx{}", get_option!(config, "max-ram"));
-                        
-                        let mut server = Command::new(java_path)
-                            .current_dir(server_folder)
-                            .args(&[min_ram.as_str(), max_ram.as_str(), "-d64", "-server",
-                                "-XX:+AggressiveOpts", "-XX:+UseConcMarkSweepGC",
-                                "-XX:+UnlockExperimentalVMOptions", "-XX:+UseParNewGC",
-                                "-XX:+ExplicitGCInvokesConcurrent", "-XX:+UseFastAccessorMethods",
-                                "-XX:+OptimizeStringConcat", "-XX:+UseAdaptiveGCBoundary",
-                                "-jar", server_path,
-                                "nogui",
-                            ])
-                            .stdout(Stdio::piped())
-                            .spawn()?;
+                        if update_process.is_some() {
+                            send_discord("An update is running, wait for it to finish".to_string());
+                            continue;
+                        }
 
-                        if let Some(stdout) = server.stdout.take() {
-                            let thread_config = config.clone();
-                            let thread_send = server_log_send.clone();
-    
-                            thread::spawn(move || {
-                                server_log_thread(thread_config, stdout, thread_send).unwrap();
-                            });
+                        if let Some(pid) = orphan_pid {
+                            send_discord(format!("A server from before the bot restarted is still running (pid {}), stop it with `{prefix}kill` first",
+                                pid, prefix = config.prefix));
+                            continue;
                         }
 
-                        let start_time = Local::now();
+                        if bot_state.maintenance {
+                            let reason = config.maintenance_message.as_str();
+                            send_discord(format!("{}, it can't be started right now", reason));
+                            continue;
+                        }
 
-                        server_status = ServerStatus::Starting{ server, start_time };
-                        send_discord("Server starting now, ETA 3 minutes".to_string());
-                        info!("Server started.");
-                    },
+                        let log_file = log_source_file(&config);
+                        let spawn_server = || -> Result<Child, Box<dyn Error>> {
+                            let min_ram = format!("-Xms{}", config.min_ram);
+                            let max_ram = format!("-Xmx{}", config.max_ram);
 
-                    Ok(FromDiscord::StopServerEvent) => {
-                        let mut server_process = None;
-                        match server_status {
-                            ServerStatus::Offline
-                            | ServerStatus::Starting{..} => {
-                                send_discord("Server's not running (yet)".to_string());
+                            Ok(Command::new(&config.java_path)
+                                .current_dir(&config.server_folder)
+                                .args(&[min_ram.as_str(), max_ram.as_str()])
+                                .args(&config.jvm_flags)
+                                .args(&["-jar", config.server_path.as_str(), "nogui"])
+                                .stdout(if log_file.is_some() { Stdio::null() } else { Stdio::piped() })
+                                .stderr(Stdio::piped())
+                                .spawn()?)
+                        };
+
+                        // A bad path shouldn't take the bot down with it
+                        let mut server = match spawn_server() {
+                            Ok(server) => server,
+                            Err(x) => {
+                                send_discord(message_format::error("Server failed to start", x.to_string().as_str()));
+                                error!("Server failed to start! - {}", x);
                                 continue;
-                            },
+                            }
+                        };
+
+                        if let Some(stdout) = server.stdout.take() {
+                            spawn_server_log_thread(&config, stdout, server_log_send.clone());
+                        }
 
-                            ServerStatus::Running{ server } => {
-                                server_process = Some(server);
+                        // The server rotates the old log when it starts, which the tail follows
+                        if let Some(log_file) = &log_file {
+                            let active = Arc::new(AtomicBool::new(true));
+                            match LogTail::open(log_file, active.clone()) {
+                                Ok(tail) => {
+                                    spawn_server_log_thread(&config, tail, server_log_send.clone());
+                                    log_tail = Some(active);
+                                },
+                                Err(x) => {
+                                    send_discord(message_format::warning(format!("Could not open {}, chat and player tracking are down", log_file.display()).as_str()));
+                                    error!("Could not open {} - {}", log_file.display(), x);
+                                }
                             }
+                        }
 
-                            ServerStatus::Stopping{..} => {
-                                send_discord("Server's already stopping".to_string());
-                                continue;
+                        // stderr only matters for errors, so it doesn't report PipeClosed
+                        if let Some(stderr) = server.stderr.take() {
+                            let thread_config = config.clone();
+                            let thread_send = server_log_send.clone();
+
+                            thread::spawn(move || {
+                                if let Err(x) = server_log_thread(thread_config, stderr, thread_send, true) {
+                                    error!("Server stderr thread failed! - {}", x);
+                                }
+                            });
+                        }
+
+                        let start_time = Local::now();
+
+                        state!().max_players = match server_properties::read_properties(&config.server_folder) {
+                            Ok(properties) => properties.get("max-players").and_then(|x| x.trim().parse().ok()),
+                            Err(x) => {
+                                warn!("Could not read server.properties - {}", x);
+                                None
                             }
-                            _ => ()
+                        };
+
+                        startup_error = None;
+                        startup_output = None;
+                        startup_warned = false;
+                        stop_announced = false;
+                        stop_logged = false;
+                        {
+                            let mut state = state!();
+                            state.version = None;
+                            state.online_players.clear();
+                        }
+                        player_stats.reset_session();
+
+                        let stale_sessions = playtime.discard_sessions();
+                        if stale_sessions > 0 {
+                            warn!("Discarded {} playtime sessions from a previous server run", stale_sessions);
+                        }
+
+                        set_status!(ServerStatus::Starting{ server, start_time });
+                        send_discord(message_format::status("Server starting now, ETA 3 minutes"));
+                        info!("Server started.");
+                    },
+
+                    Ok(FromDiscord::StopServerEvent) => {
+                        if matches!(server_status, ServerStatus::Stopping{..}) {
+                            send_discord("Server's already stopping".to_string());
+                            continue;
+                        }
+                        if let Err(msg) = require_running(&server_status) {
+                            send_discord(msg.to_string());
+                            continue;
                         }
-                        let rcon = Some(Command::new(get_option!(config, "mcrcon-path"))
-                            .args(&["-P", "25564", "-p", get_option!(config, "rcon_password"), "-s",
-                                "-w", "60",
-                                "say Shutting down in 5 minutes",
-                                "say Shutting down in 4 minutes",
-                                "say Shutting down in 3 minutes",
-                                "say Shutting down in 2 minutes",
-                                "say Shutting down in 1 minute",
-                                "shutdown",
-                            ])
-                            .stdin(Stdio::null())
-                            .spawn()?);
-                        server_status = ServerStatus::Stopping{ server: server_process, rcon };
-                        send_discord("Server will be stopped in 5 minutes, type `mc!cancel` to cancel".to_string());
+                        let rcon = match run_rcon(&config, &[
+                            "-w", "60",
+                            "say Shutting down in 5 minutes",
+                            "say Shutting down in 4 minutes",
+                            "say Shutting down in 3 minutes",
+                            "say Shutting down in 2 minutes",
+                            "say Shutting down in 1 minute",
+                            "shutdown",
+                        ]) {
+                            Ok(rcon) => Some(rcon),
+                            Err(x) => {
+                                send_discord(message_format::error("Could not stop the server", x.to_string().as_str()));
+                                error!("Failed to stop the server! - {}", x);
+                                continue;
+                            }
+                        };
+                        let (server_process, server_start_time) = match server_status {
+                            ServerStatus::Running{ server, start_time } => (Some(server), Some(start_time)),
+                            _ => (None, None)
+                        };
+                        set_status!(ServerStatus::Stopping{ server: server_process, start_time: server_start_time, rcon, initiator: StopInitiator::Admin });
+                        send_discord(message_format::status(format!("Server will be stopped in 5 minutes, type `{prefix}cancel` to cancel", prefix = config.prefix).as_str()));
                         info!("Server stop started.");
                     },
 
                     Ok(FromDiscord::KillServerEvent) => {
-                        let mut server_process = None;
-                        match server_status {
-                            ServerStatus::Offline
-                            | ServerStatus::Starting{..} => {
-                                send_discord("Server's not running (yet)".to_string());
+                        if matches!(server_status, ServerStatus::Stopping{..}) {
+                            send_discord("Server's already stopping".to_string());
+                            continue;
+                        }
+                        if let Err(msg) = require_running(&server_status) {
+                            send_discord(msg.to_string());
+                            continue;
+                        }
+                        let rcon = match run_rcon(&config, &["shutdown"]) {
+                            Ok(rcon) => Some(rcon),
+                            Err(x) => {
+                                send_discord(message_format::error("Could not stop the server", x.to_string().as_str()));
+                                error!("Failed to stop the server! - {}", x);
                                 continue;
-                            },
-
-                            ServerStatus::Running{ server } => {
-                                server_process = Some(server);
                             }
+                        };
+                        let (server_process, server_start_time) = match server_status {
+                            ServerStatus::Running{ server, start_time } => (Some(server), Some(start_time)),
+                            _ => (None, None)
+                        };
+                        set_status!(ServerStatus::Stopping{ server: server_process, start_time: server_start_time, rcon, initiator: StopInitiator::Admin });
+                        send_discord(message_format::status("Server is stopping now"));
+                        info!("Server killed.");
+                    },
 
-                            ServerStatus::Stopping{..} => {
-                                send_discord("Server's already stopping".to_string());
+                    Ok(FromDiscord::RestartServerEvent) => {
+                        if matches!(server_status, ServerStatus::Stopping{..}) {
+                            send_discord("Server's already stopping".to_string());
+                            continue;
+                        }
+                        if let Err(msg) = require_running(&server_status) {
+                            send_discord(msg.to_string());
+                            continue;
+                        }
+                        let rcon = match run_rcon(&config, &["say Restarting the server", "shutdown"]) {
+                            Ok(rcon) => Some(rcon),
+                            Err(x) => {
+                                send_discord(message_format::error("Could not restart the server", x.to_string().as_str()));
+                                error!("Failed to restart the server! - {}", x);
                                 continue;
                             }
-                            _ => ()
+                        };
+                        let (server_process, server_start_time) = match server_status {
+                            ServerStatus::Running{ server, start_time } => (Some(server), Some(start_time)),
+                            _ => (None, None)
+                        };
+                        set_status!(ServerStatus::Stopping{ server: server_process, start_time: server_start_time, rcon, initiator: StopInitiator::Restart });
+                        restart_pending = Some(reply_channel);
+                        send_discord(message_format::status("Server is restarting now"));
+                        info!("Server restart started.");
+                    },
+
+                    Ok(FromDiscord::ShutdownServerEvent(hour, minute)) => {
+                        if !matches!(server_status, ServerStatus::Running{..}) {
+                            send_discord("Server's not running".to_string());
+                            continue;
+                        }
+
+                        let now = Local::now();
+                        match next_occurrence(now, hour, minute) {
+                            Some(time) => {
+                                state!().scheduled_shutdown = Some(time);
+                                // Warnings that are already due are covered by this announcement
+                                restart_warned.clear();
+                                restart_warning_due(Some(time), &config.restart_warnings(), &mut restart_warned, now);
+                                send_discord(message_format::status(format!("Server will be stopped at {} (in {}), type `{prefix}cancel` to cancel",
+                                    time.format("%H:%M"), format_duration(time - now), prefix = config.prefix).as_str()));
+                                info!("Shutdown scheduled for {}.", time);
+                                rcon_in_background(&config, format!("say Server will shut down at {}", time.format("%H:%M")));
+                            },
+                            None => send_discord(format!("{:02}:{:02} doesn't exist today or tomorrow", hour, minute)),
                         }
-                        let rcon = Some(Command::new(get_option!(config, "mcrcon-path"))
-                            .args(&["-P", "25564", "-p", get_option!(config, "rcon_password"), "-s",
-                                "shutdown",
-                            ])
-                            .stdin(Stdio::null())
-                            .spawn()?);
-                        server_status = ServerStatus::Stopping{ server: server_process, rcon };
-                        send_discord("Server is stopping now".to_string());
-                        info!("Server killed.");
                     },
 
-                    Ok(FromDiscord::ShutdownServerEvent(_h, _m)) => {
-                        send_discord("Unimplemented, to be added later".to_string());
+                    Ok(FromDiscord::UsageError { command, usage }) => {
+                        send_discord(format!("Usage: `{}{} {}`", config.prefix, command, usage));
                     },
 
-                    Ok(FromDiscord::CancelShutdownEvent) => {
-                        match &mut server_status {
-                            ServerStatus::Stopping{ rcon: Some(rcon), .. } => {
-                                if let Err(_) = rcon.kill() {
-                                    send_discord("Error while cancelling shutdown".to_string());
-                                } else {
-                                    send_discord("Shutdown cancelled".to_string());
-                                }
+                    Ok(FromDiscord::InvalidTimeEvent(problem)) => {
+                        send_discord(format!("{}, try `{prefix}shutdown hh:mm`", problem, prefix = config.prefix));
+                    },
+
+                    Ok(FromDiscord::CancelShutdownEvent(user)) => {
+                        let (new_status, outcome) = cancel_stop(server_status);
+                        set_status!(new_status);
+                        if outcome == CancelOutcome::Cancelled {
+                            restart_pending = None;
+                        }
+
+                        match outcome {
+                            CancelOutcome::Cancelled => {
+                                send_discord(message_format::status(format!("Shutdown cancelled by {}", user).as_str()));
+                                info!("Shutdown cancelled by {}.", user);
 
-                                let _rcon = Command::new(get_option!(config, "mcrcon-path"))
-                                    .args(&["-P", "25564", "-p", get_option!(config, "rcon_password"), "-s",
-                                        "say Shutdown cancelled",
-                                    ])
-                                    .stdin(Stdio::null())
-                                    .spawn()?;
-                                
-                                if let ServerStatus::Stopping{ server: Some(server), .. } = server_status {
-                                    server_status = ServerStatus::Running{server};
-                                } else {
-                                    server_status = ServerStatus::Unknown;
+                                if let Err(x) = rcon_output(&config, &[format!("say Shutdown cancelled by {}", user).as_str()]) {
+                                    error!("Failed to announce the cancel in game! - {}", x);
                                 }
                             },
-
-                            ServerStatus::Stopping{ rcon: None, .. } => {
-                                send_discord("Shutdown cannot be cancelled".to_string());
-                                continue;
+                            CancelOutcome::TooLate => send_discord("Too late, the server already stopped".to_string()),
+                            CancelOutcome::Failed(x) => {
+                                send_discord("Error while cancelling shutdown".to_string());
+                                error!("Failed to cancel shutdown! - {}", x);
                             },
-
-                            ServerStatus::Offline => {
-                                send_discord("Server's not running".to_string());
-                                continue;
+                            CancelOutcome::NotCancellable => send_discord("Shutdown cannot be cancelled".to_string()),
+                            CancelOutcome::NotRunning => send_discord("Server's not running".to_string()),
+                            CancelOutcome::NothingToCancel => match cancel_scheduled_shutdown(&mut state!().scheduled_shutdown, &mut restart_warned, &user) {
+                                Some(reply) => {
+                                    send_discord(message_format::status(reply.as_str()));
+                                    info!("Scheduled shutdown cancelled by {}.", user);
+                                    rcon_in_background(&config, format!("say Scheduled shutdown cancelled by {}", user));
+                                },
+                                None => send_discord("No shutdown in progress".to_string()),
                             },
+                        }
+                    },
 
-                            _ => {
-                                send_discord("No shutdown in progress".to_string());
+                    Ok(FromDiscord::UpdateEvent) => {
+                        let update_command = match config.update_command.clone() {
+                            Some(update_command) => update_command,
+                            None => {
+                                send_discord("No update_command in config file".to_string());
                                 continue;
                             }
+                        };
+
+                        if !matches!(server_status, ServerStatus::Offline | ServerStatus::Unknown) {
+                            send_discord("Stop the server before updating".to_string());
+                            continue;
                         }
-                        info!("Shutdown cancelled.");
-                    },
 
-                    Ok(FromDiscord::BackupEvent) => {
-                        match server_status {
-                            ServerStatus::Offline
-                            | ServerStatus::Starting{..} => {
-                                send_discord("Server's not running (yet)".to_string());
-                                continue;
-                            },
+                        if update_process.is_some() {
+                            send_discord("An update is already running".to_string());
+                            continue;
+                        }
 
-                            ServerStatus::Stopping{..} => {
-                                send_discord("Server's stopping".to_string());
-                                continue;
+                        let spawn_update = || -> Result<Child, Box<dyn Error>> {
+                            let update_log = File::create(&config.update_log)?;
+
+                            Ok(shell_command(&update_command)
+                                .stdin(Stdio::null())
+                                .stdout(update_log.try_clone()?)
+                                .stderr(update_log)
+                                .spawn()?)
+                        };
+
+                        match spawn_update() {
+                            Ok(update) => {
+                                update_process = Some(update);
+                                send_discord(message_format::status("Update started"));
+                                warn!("Update started: {}", update_command);
+                            },
+                            Err(x) => {
+                                send_discord(message_format::error("Update failed to start", x.to_string().as_str()));
+                                error!("Update failed to start - {}", x);
                             }
-                            _ => ()
                         }
-                        Command::new(get_option!(config, "mcrcon-path"))
-                            .args(&["-P", "25564", "-p", get_option!(config, "rcon_password"), "-s",
-                                "backup start",
-                            ])
-                            .stdin(Stdio::null())
-                            .spawn()?;
+                    },
+
+                    Ok(FromDiscord::BackupEvent) => {
+                        if let Err(msg) = check_backup_allowed(&server_status, backup_in_progress) {
+                            send_discord(msg.to_string());
+                            continue;
+                        }
+                        if let Err(x) = rcon_output(&config, &["backup start"]) {
+                            send_discord(message_format::error("Could not start the backup", x.to_string().as_str()));
+                            error!("Failed to start the backup! - {}", x);
+                            continue;
+                        }
+                        backup_in_progress = true;
                         info!("Backup started.");
                         send_discord("Backup started.".to_string());
                     },
@@ -380,69 +1312,507 @@ fn main_thread(config: &Value, bot: Discord) -> Result<(), Box<dyn Error>> {
                             send_discord("Must provide a username to op".to_string());
                             continue;
                         }
-                        match server_status {
-                            ServerStatus::Offline
-                            | ServerStatus::Starting{..} => {
-                                send_discord("Server's not running (yet)".to_string());
-                                continue;
-                            },
-
-                            ServerStatus::Stopping{..} => {
-                                send_discord("Server's stopping".to_string());
-                                continue;
-                            }
-                            _ => ()
+                        if let Err(msg) = require_running(&server_status) {
+                            send_discord(msg.to_string());
+                            continue;
                         }
                         let op_user = format!("op {}", user);
-                        Command::new(get_option!(config, "mcrcon-path"))
-                            .args(&["-P", "25564", "-p", get_option!(config, "rcon_password"), "-s",
-                                "backup start",
-                                op_user.as_str(),
-                            ])
-                            .stdin(Stdio::null())
-                            .spawn()?;
-                        warn!("Opped user {} by command", user);
+                        if let Err(x) = rcon_output(&config, &[op_user.as_str()]) {
+                            send_discord(message_format::error("Could not op the user", x.to_string().as_str()));
+                            error!("Failed to op {}! - {}", user, x);
+                            continue;
+                        }
+                        warn!("Opped user {} by {}", user, issuer);
                         send_discord(format!("Opped user {}. All ops are logged.\nDon't forget to de-op yourself after you're done!", user));
                     },
 
+                    Ok(FromDiscord::DeopCommandEvent(user)) => {
+                        if user.is_empty() {
+                            send_discord("Must provide a username to de-op".to_string());
+                            continue;
+                        }
+                        if let Err(msg) = require_running(&server_status) {
+                            send_discord(msg.to_string());
+                            continue;
+                        }
+                        let deop_user = format!("deop {}", user);
+                        if let Err(x) = rcon_output(&config, &[deop_user.as_str()]) {
+                            send_discord(message_format::error("Could not de-op the user", x.to_string().as_str()));
+                            error!("Failed to de-op {}! - {}", user, x);
+                            continue;
+                        }
+                        warn!("De-opped user {} by {}", user, issuer);
+                        send_discord(format!("De-opped user {}.", user));
+                    },
+
+                    Ok(FromDiscord::WhitelistAddEvent(user)) |
+                    Ok(FromDiscord::WhitelistRemoveEvent(user)) if user.is_empty() => {
+                        send_discord("Must provide a username to whitelist".to_string());
+                    },
+
+                    Ok(FromDiscord::WhitelistAddEvent(user)) => {
+                        if let Err(msg) = require_running(&server_status) {
+                            send_discord(msg.to_string());
+                            continue;
+                        }
+                        if let Err(x) = rcon_output(&config, &[format!("whitelist add {}", user).as_str()]) {
+                            send_discord(message_format::error("Could not whitelist the user", x.to_string().as_str()));
+                            error!("Failed to whitelist {}! - {}", user, x);
+                            continue;
+                        }
+                        warn!("Whitelisted user {} by {}", user, issuer);
+                        send_discord(format!("Added {} to the whitelist.", user));
+                    },
+
+                    Ok(FromDiscord::WhitelistRemoveEvent(user)) => {
+                        if let Err(msg) = require_running(&server_status) {
+                            send_discord(msg.to_string());
+                            continue;
+                        }
+                        if let Err(x) = rcon_output(&config, &[format!("whitelist remove {}", user).as_str()]) {
+                            send_discord(message_format::error("Could not remove the user from the whitelist", x.to_string().as_str()));
+                            error!("Failed to remove {} from the whitelist! - {}", user, x);
+                            continue;
+                        }
+                        warn!("Removed user {} from the whitelist by {}", user, issuer);
+                        send_discord(format!("Removed {} from the whitelist.", user));
+                    },
+
+                    Ok(FromDiscord::RawRconEvent(command)) => {
+                        if command.trim().is_empty() {
+                            send_discord("Must provide a command to run".to_string());
+                            continue;
+                        }
+                        if !server_status.is_up() {
+                            send_discord("Server's not running".to_string());
+                            continue;
+                        }
+
+                        let commands: Vec<String> = if config.rcon_allow_newlines {
+                            command.lines().map(str::trim).filter(|x| !x.is_empty()).map(String::from).collect()
+                        } else {
+                            vec![command.replace(&['\n', '\r'][..], " ").trim().to_string()]
+                        };
+                        let command_refs: Vec<&str> = commands.iter().map(String::as_str).collect();
+
+                        warn!("Ran RCON command by {}: {}", issuer, commands.join(" | "));
+                        match rcon_output(&config, &command_refs) {
+                            Ok(output) if output.trim().is_empty() => send_discord("Command sent, no output.".to_string()),
+                            Ok(output) => {
+                                for chunk in message_format::code_block_chunks(output.as_str()) {
+                                    send_discord(chunk);
+                                }
+                            },
+                            Err(x) => {
+                                send_discord(message_format::error("Failed to run RCON command", x.to_string().as_str()));
+                                error!("Failed to run RCON command - {}", x);
+                            }
+                        }
+                    },
+
                     Ok(FromDiscord::StatusQueryEvent) => {
-                        match server_status {
-                            ServerStatus::Offline => {
-                                send_discord("Server is offline.".to_string());
+                        let state = state!();
+                        let mut status_str = match server_status {
+                            ServerStatus::Offline => "Server is offline.".to_string(),
+                            ServerStatus::Unknown => "Server is probably offline, but worth a try.".to_string(),
+                            ServerStatus::Starting{..} => "Server is starting, check back in a few mins.".to_string(),
+                            ServerStatus::Running{..} => {
+                                let uptime = format_duration(state.uptime(Local::now()).unwrap_or_else(Duration::zero));
+                                match state.max_players {
+                                    Some(max_players) => format!("Server is running for {}, {}/{} online.", uptime, state.online_players.len(), max_players),
+                                    None => format!("Server is running for {}, {} online.", uptime, state.online_players.len()),
+                                }
                             },
-                            ServerStatus::Unknown => {
-                                send_discord("Server is probably offline, but worth a try.".to_string());
+                            ServerStatus::Stopping{..} => "Server is stopping.".to_string(),
+                            ServerStatus::Monitoring{ up: true } => format!("Server is running (not managed by the bot), {} online.", state.online_players.len()),
+                            ServerStatus::Monitoring{ up: false } => "Server is offline (not managed by the bot).".to_string(),
+                        };
+
+                        if let (true, Some(version)) = (server_status.is_up(), &state.version) {
+                            status_str += format!("\nVersion: {}", version).as_str();
+                        }
+
+                        if bot_state.maintenance {
+                            status_str += "\nMaintenance mode is on, starting is disabled.";
+                        }
+
+                        if let Some((finish_time, time)) = state.last_backup() {
+                            status_str += format!("\nLast backup: {} ago, took {}s",
+                                format_duration(Local::now() - finish_time), time.num_seconds()).as_str();
+                        }
+
+                        send_discord(status_str);
+                    },
+
+                    Ok(FromDiscord::BackupStatsQueryEvent) => {
+                        let backup_history = state!().backup_history.clone();
+                        if backup_history.is_empty() {
+                            send_discord("No backups finished since the bot started.".to_string());
+                            continue;
+                        }
+
+                        let total_secs: i64 = backup_history.iter().map(|(_, time)| time.num_seconds()).sum();
+                        let longest_secs = backup_history.iter().map(|(_, time)| time.num_seconds()).max().unwrap_or_default();
+
+                        let mut message_str = format!("Last {} backups - average {}s, longest {}s:",
+                            backup_history.len(), total_secs / backup_history.len() as i64, longest_secs);
+                        for (finish_time, time) in backup_history.iter().rev() {
+                            message_str += format!("\n{} - took {}s", finish_time.format("%Y-%m-%d %H:%M"), time.num_seconds()).as_str();
+                        }
+                        send_discord(message_str);
+                    },
+
+                    Ok(FromDiscord::PlayersQueryEvent) => {
+                        // The server's own list, the players tracked from the log can miss a login while the log wasn't read
+                        let player_list = if server_status.is_up() {
+                            rcon_output(&config, &["list"]).ok()
+                                .and_then(|output| player_stats::parse_player_list(output.as_str()))
+                        } else {
+                            None
+                        };
+
+                        let (mut names, max_players) = match player_list {
+                            Some(PlayerList { names, max, .. }) => (names, Some(max)),
+                            None => {
+                                let state = state!();
+                                (state.online_players.iter().cloned().collect::<Vec<_>>(), state.max_players)
                             },
-                            ServerStatus::Starting{..} => {
-                                send_discord("Server is starting, check back in a few mins.".to_string());
+                        };
+                        names.sort_unstable();
+                        let names: Vec<String> = names.iter().map(|name| config.display_name(name)).collect();
+
+                        let online_str = if !server_status.is_up() {
+                            "Server's not running.".to_string()
+                        } else if names.is_empty() {
+                            "Nobody is online.".to_string()
+                        } else {
+                            match max_players {
+                                Some(max_players) => format!("{}/{} online: {}", names.len(), max_players, names.join(", ")),
+                                None => format!("{} online: {}", names.len(), names.join(", ")),
+                            }
+                        };
+
+                        send_discord(format!(
+                            "{}\nThis session - peak: {}, unique: {}\nAll time - peak: {}, unique: {}",
+                            online_str,
+                            player_stats.session_peak, player_stats.session_unique.len(),
+                            player_stats.peak_players, player_stats.unique_players.len()
+                        ));
+                    },
+
+                    Ok(FromDiscord::TpsQueryEvent) => {
+                        if !server_status.is_up() {
+                            send_discord("Server's not running.".to_string());
+                            continue;
+                        }
+
+                        match rcon_output(&config, &[config.tps_command.as_str()]) {
+                            Ok(output) => match tps::parse_tps(output.as_str()) {
+                                Some(tps) => send_discord(format!("Server TPS: {:.1}", tps)),
+                                // Unknown format, the raw output is still more useful than nothing
+                                None => for chunk in message_format::code_block_chunks(message_format::minecraft_formatting(output.as_str(), false).as_str()) {
+                                    send_discord(chunk);
+                                }
                             },
-                            ServerStatus::Running{..} => {
-                                send_discord("Server is running.".to_string());
+                            Err(x) => {
+                                send_discord(message_format::error("Failed to query TPS", x.to_string().as_str()));
+                                error!("Failed to run `{}` - {}", config.tps_command, x);
+                            }
+                        }
+                    },
+
+                    Ok(FromDiscord::PlaytimeQueryEvent(name)) => {
+                        let now = Local::now();
+
+                        if name.is_empty() {
+                            let mut names: Vec<String> = state!().online_players.iter().cloned().collect();
+                            names.sort_unstable();
+
+                            if names.is_empty() {
+                                send_discord("Nobody is online.".to_string());
+                                continue;
+                            }
+
+                            let mut message_str = "Playtime of online players:".to_string();
+                            for name in names {
+                                let time = playtime.total(&name, now).unwrap_or_else(Duration::zero);
+                                message_str += format!("\n**{}** - {}", name, format_duration(time)).as_str();
+                            }
+                            send_discord(message_str);
+                        } else {
+                            match playtime.total(&name, now) {
+                                Some(time) => send_discord(format!("**{}** has played for {}", name, format_duration(time))),
+                                None => send_discord(format!("{} has never played here", name)),
+                            }
+                        }
+                    },
+
+                    Ok(FromDiscord::LeaderboardQueryEvent) => {
+                        let board = playtime.leaderboard(Local::now());
+                        if board.is_empty() {
+                            send_discord("Nobody has played yet.".to_string());
+                            continue;
+                        }
+
+                        let mut message_str = "Playtime leaderboard:".to_string();
+                        for (place, (name, time)) in board.iter().take(10).enumerate() {
+                            message_str += format!("\n{}. **{}** - {}", place + 1, name, format_duration(*time)).as_str();
+                        }
+                        send_discord(message_str);
+                    },
+
+                    Ok(FromDiscord::VersionQueryEvent) => {
+                        match option_env!("GIT_COMMIT") {
+                            Some(commit) => send_discord(format!("Server maintainer ver {} ({})", clap::crate_version!(), commit)),
+                            None => send_discord(format!("Server maintainer ver {}", clap::crate_version!())),
+                        }
+                    },
+
+                    Ok(FromDiscord::MemoryQueryEvent) => {
+                        let mut message_str = match server_status.pid().map(system_info::process_memory) {
+                            Some(Some(used)) => format!("Server is using {} (max heap {})",
+                                system_info::format_kb(used), config.max_ram),
+                            Some(None) => "Server memory usage is not available on this system".to_string(),
+                            None => "Server's not running".to_string(),
+                        };
+
+                        if let Some(memory) = system_info::system_memory() {
+                            message_str += format!("\nSystem: {} used of {}",
+                                system_info::format_kb(memory.total - memory.available), system_info::format_kb(memory.total)).as_str();
+                        }
+
+                        send_discord(message_str);
+                    },
+
+                    Ok(FromDiscord::ResourceQueryEvent) => {
+                        let server_pid = server_status.pid();
+
+                        // CPU usage needs two samples
+                        let cpu_before = system_info::cpu_times();
+                        let server_before = server_pid.and_then(system_info::process_cpu_time);
+                        thread::sleep(std::time::Duration::from_millis(500));
+                        let cpu_after = system_info::cpu_times();
+                        let server_after = server_pid.and_then(system_info::process_cpu_time);
+
+                        let total_ticks = match (cpu_before, cpu_after) {
+                            (Some((_, before)), Some((_, after))) if after > before => Some(after - before),
+                            _ => None
+                        };
+
+                        let mut host = Vec::<String>::new();
+                        if let (Some((idle_before, _)), Some((idle_after, _)), Some(total)) = (cpu_before, cpu_after, total_ticks) {
+                            let busy = total.saturating_sub(idle_after.saturating_sub(idle_before));
+                            host.push(format!("CPU {}%", busy * 100 / total));
+                        }
+                        if let Some(load) = system_info::load_average() {
+                            host.push(format!("load {}", load));
+                        }
+                        if let Some(memory) = system_info::system_memory() {
+                            host.push(format!("RAM {} used of {}",
+                                system_info::format_kb(memory.total - memory.available), system_info::format_kb(memory.total)));
+                        }
+
+                        let mut message_str = if host.is_empty() {
+                            "Host usage is not available on this system".to_string()
+                        } else {
+                            format!("Host: {}", host.join(", "))
+                        };
+
+                        match server_pid {
+                            Some(pid) => {
+                                let mut server = Vec::<String>::new();
+                                if let (Some(before), Some(after), Some(total)) = (server_before, server_after, total_ticks) {
+                                    server.push(format!("CPU {}% of host", after.saturating_sub(before) * 100 / total));
+                                }
+                                if let Some(used) = system_info::process_memory(pid) {
+                                    server.push(format!("RAM {}", system_info::format_kb(used)));
+                                }
+                                if !server.is_empty() {
+                                    message_str += format!("\nServer: {}", server.join(", ")).as_str();
+                                }
                             },
-                            ServerStatus::Stopping{..} => {
-                                send_discord("Server is stopping.".to_string());
+                            None => message_str += "\nServer's not running",
+                        }
+
+                        send_discord(message_str);
+                    },
+
+                    Ok(FromDiscord::MotdEvent(motd)) => {
+                        let server_folder = config.server_folder.as_str();
+
+                        if motd.is_empty() {
+                            match server_properties::read_properties(server_folder) {
+                                Ok(properties) => match properties.get("motd") {
+                                    Some(motd) => send_discord(format!("Current MOTD: {}", server_properties::unescape_value(motd))),
+                                    None => send_discord("No MOTD set in server.properties".to_string()),
+                                },
+                                Err(x) => {
+                                    send_discord("Could not read server.properties".to_string());
+                                    error!("Could not read server.properties - {}", x);
+                                }
+                            }
+                            continue;
+                        }
+
+                        match server_properties::write_property(server_folder, "motd", motd.as_str()) {
+                            Ok(()) => {
+                                send_discord("MOTD updated, it takes effect on the next server start.".to_string());
+                                warn!("MOTD changed to {} by {}", motd, issuer);
                             },
+                            Err(x) => {
+                                send_discord("Could not update server.properties".to_string());
+                                error!("Could not update server.properties - {}", x);
+                            }
                         }
                     },
 
+                    Ok(FromDiscord::ReloadConfigEvent) => {
+                        let new_config = match load_config(config_path) {
+                            Ok(new_config) => new_config,
+                            Err(x) => {
+                                send_discord(format!("Invalid config, keeping the old one - {}", x));
+                                continue;
+                            }
+                        };
+
+                        let problems = preflight::validate_config(&new_config);
+                        if !problems.is_empty() {
+                            send_discord(message_format::error("Invalid config, keeping the old one", problems.join("\n").as_str()));
+                            continue;
+                        }
+
+                        CONSOLE_ENABLED.store(new_config.console_enabled, Ordering::Relaxed);
+
+                        *shared_config.write().unwrap_or_else(PoisonError::into_inner) = new_config.clone();
+                        config = new_config;
+                        tps_window = TpsWindow::new(config.tps_alert_window());
+
+                        send_discord(format!("Config reloaded. The prefix is now `{}`, channels and permissions apply right away, RAM and JVM settings on the next server start.", config.prefix));
+                        info!("Config reloaded.");
+                    },
+
+                    Ok(FromDiscord::MaintenanceEvent(Some(maintenance))) => {
+                        bot_state.maintenance = maintenance;
+                        if let Err(x) = bot_state.save(&state_path) {
+                            error!("Failed to save bot state! - {}", x);
+                        }
+
+                        if maintenance {
+                            send_discord(message_format::status("Maintenance mode on, the server can't be started"));
+                        } else {
+                            send_discord(message_format::status("Maintenance mode off"));
+                        }
+                        info!("Maintenance mode {}.", if maintenance { "on" } else { "off" });
+                    },
+                    Ok(FromDiscord::MaintenanceEvent(None)) => {
+                        send_discord(format!("Maintenance mode is {}, use `{prefix}maintenance on|off` to change it",
+                            if bot_state.maintenance { "on" } else { "off" }, prefix = config.prefix));
+                    },
+
+                    Ok(FromDiscord::ScheduleQueryEvent) => {
+                        let now = Local::now();
+                        let mut schedule = Vec::<String>::new();
+
+                        if let Some(time) = state!().scheduled_shutdown {
+                            schedule.push(format!("Shutdown at {} (in {})", time.format("%H:%M"), format_duration(time - now)));
+                        }
+
+                        if let (Some(startup_timeout), ServerStatus::Starting{ start_time, .. }) = (config.startup_timeout(), &server_status) {
+                            if !startup_warned {
+                                let action = if config.startup_timeout_kill { "Startup timeout kill" } else { "Startup timeout warning" };
+                                schedule.push(format!("{} at {}", action, (*start_time + startup_timeout).format("%H:%M")));
+                            }
+                        }
+
+                        if let (Some(reminder_timeout), Some(since), ServerStatus::Running{..}) = (config.empty_reminder_timeout(), empty_since, &server_status) {
+                            if !empty_reminder_sent {
+                                let remaining = std::cmp::max(since + reminder_timeout - now, Duration::zero());
+                                schedule.push(format!("Empty server reminder in {}", format_duration(remaining)));
+                            }
+                        }
+
+                        if schedule.is_empty() {
+                            send_discord("Nothing is scheduled".to_string());
+                        } else {
+                            send_discord(format!("Scheduled:\n{}", schedule.join("\n")));
+                        }
+                    },
+
+                    Ok(FromDiscord::RestartWarningQueryEvent) => {
+                        let warnings = config.restart_warnings();
+                        if warnings.is_empty() {
+                            send_discord("No shutdown warnings set, add `restart_warning_minutes` to the config file".to_string());
+                            continue;
+                        }
+
+                        match state!().scheduled_shutdown {
+                            Some(time) => {
+                                let lines: Vec<String> = warnings.iter().map(|offset| {
+                                    let sent = if restart_warned.contains(offset) { " - sent" } else { "" };
+                                    format!("{} left at {}{}", format_duration(*offset), (time - *offset).format("%H:%M"), sent)
+                                }).collect();
+                                send_discord(format!("Warnings before the shutdown at {}:\n{}", time.format("%H:%M"), lines.join("\n")));
+                            },
+                            None => {
+                                let offsets: Vec<String> = warnings.iter().map(|offset| format_duration(*offset)).collect();
+                                send_discord(format!("Players are warned {} before a scheduled shutdown, none is scheduled", offsets.join(", ")));
+                            }
+                        }
+                    },
+
+                    Ok(FromDiscord::RestartReactionEvent(_)) => (),
+                    Ok(FromDiscord::RestartOfferedEvent(message)) => restart_message = Some(message),
+
+                    Ok(FromDiscord::ChatRelay { author, content }) => {
+                        if !config.relay.discord_chat || !server_status.is_up() {
+                            continue;
+                        }
+
+                        rcon_in_background(&config, message_format::game_chat_command(&author, &content));
+                    },
+
                     Ok(FromDiscord::HelpEvent) => {
                         send_discord(format!(
                             r#"Commands:
     `{prefix}start` - Starts the server
     `{prefix}stop` - Stops the server
     `{prefix}kill` - Stops the server without waiting 5 mins
+    `{prefix}restart` - Stops the server now and starts it again
     `{prefix}cancel` - Cancels server stop
-    `{prefix}shutdown [hh:mm]` - Schedules a shutdown in CEST
+    `{prefix}shutdown <hh:mm>` - Schedules a shutdown, tomorrow if the time has passed today
     `{prefix}backup` - Starts a backup on the server (pls no spam)
+    `{prefix}backups` - Displays the recent backup history
     `{prefix}op` - Ops a user if an accident happens - all ops are logged
+    `{prefix}deop` - De-ops a user - also logged
+    `{prefix}whitelist <add|remove> <user>` - Adds or removes a user from the whitelist
+    `{prefix}cmd <command>` - Runs a server command through RCON, for the owner and `rcon_users` - all commands are logged
     `{prefix}status` - Displays server status
+    `{prefix}players` - Displays online players and peak/unique counts
+    `{prefix}tps` - Displays the server TPS, using the `tps_command` config key
+    `{prefix}playtime [name]` - Displays the playtime of a player, or everyone online
+    `{prefix}leaderboard` - Displays the players with the most playtime
+    `{prefix}motd [message]` - Displays or changes the server MOTD
+    `{prefix}memory` - Displays server and system memory usage
+    `{prefix}host` - Displays host and server CPU and RAM usage
+    `{prefix}schedule` - Displays pending scheduled actions
+    `{prefix}restart-warn` - Displays when players are warned before a scheduled shutdown
+    `{prefix}maintenance [on|off]` - Blocks or allows starting the server
+    `{prefix}update` - Runs the configured update command while the server is offline
+    `{prefix}reload` - Reloads the config file
+    `{prefix}version` - Displays the bot version
     `{prefix}help` - Displays this message"#,
-                                prefix = PREFIX
+                                prefix = config.prefix
                             ));
                     },
+                    Ok(FromDiscord::NotAllowedEvent) => {
+                        send_discord("That command is only allowed in the admin channel".to_string());
+                    },
+                    Ok(FromDiscord::UnauthorizedEvent) => {
+                        send_discord("You're not allowed to do that".to_string());
+                    },
                     Ok(FromDiscord::UnknownCommand) |
                     Ok(FromDiscord::NoCommand) => {
-                        send_discord(format!("Unknown command, try `{prefix}help` if you're stuck", prefix = PREFIX));
+                        send_discord(format!("Unknown command, try `{prefix}help` if you're stuck", prefix = config.prefix));
                     },
                     Ok(FromDiscord::ErrorEvent) => {
                         info!("Discord closed.");
@@ -450,75 +1820,296 @@ fn main_thread(config: &Value, bot: Discord) -> Result<(), Box<dyn Error>> {
                     },
                     Err(_) | Ok(FromDiscord::ReconnectEvent) => {
                         // Handle the websocket connection being dropped
-                        let config = config.clone();
-                        let (discord_send, new_from_discord) = bounded(5);
-    
-                        let (connection, ready) = bot.connect()?;
-                        let state = State::new(ready);
+                        spawn_discord_thread(&shared_config, &bot, bot_channel, &presence_recv, discord_send.clone())?;
+                        presence_sent = None;
                         info!("Reconnected successfully.");
-    
-                        thread::spawn(move || {
-                            discord_thread(config, connection, state, discord_send).unwrap();
-                        });
-    
-                        from_discord = new_from_discord;
                     },
                 }
             },
             recv(from_server_log) -> server_log_msg => {
 
                 match server_log_msg {
+                    Ok(FromServerLog::ServerVersion { version }) => {
+                        state!().version = Some(version);
+                    },
                     Ok(FromServerLog::ServerStarted) => {
                         if let ServerStatus::Starting { server, start_time } = server_status {
-                            server_status = ServerStatus::Running { server };
+                            set_status!(ServerStatus::Running { server, start_time });
+                            last_tps_poll = Local::now();
+
+                            if state!().online_players.is_empty() {
+                                empty_since = Some(Local::now());
+                                empty_reminder_sent = false;
+                            }
 
                             let elapsed_time = Local::now() - start_time;
-                            send_discord(format!("Server's now running, startup: {}s", elapsed_time.num_seconds()));
+                            match state!().version.clone() {
+                                Some(version) => send_discord(message_format::status(format!("Server's now running on {}, startup: {}s", version, elapsed_time.num_seconds()).as_str())),
+                                None => send_discord(message_format::status(format!("Server's now running, startup: {}s", elapsed_time.num_seconds()).as_str())),
+                            }
+                        } else if let ServerStatus::Monitoring{ up: false } = server_status {
+                            set_status!(ServerStatus::Monitoring{ up: true });
+                            last_tps_poll = Local::now();
+                            match state!().version.clone() {
+                                Some(version) => send_discord(message_format::status(format!("Server's now running on {}", version).as_str())),
+                                None => send_discord(message_format::status("Server's now running")),
+                            }
+                        } else if server_status.is_up() {
+                            // RFTools packs log both the RFTools and the vanilla line
+                            debug!("Server start was already detected");
                         } else {
                             error!("Server is running, but previous status was invalid");
-                            server_status = ServerStatus::Unknown;
+                            set_status!(ServerStatus::Unknown);
                         }
                     },
                     Ok(FromServerLog::ServerStopping) => {
-                        send_discord("Server is now stopping...".to_string());
-                        if let ServerStatus::Running { server } = server_status {
-                            server_status = ServerStatus::Stopping {
-                                server: Some(server),
-                                rcon: None
+                        stop_logged = true;
+                        if let ServerStatus::Monitoring{ up } = &mut server_status {
+                            *up = false;
+                            state!().online_players.clear();
+                            empty_since = None;
+                            playtime.logout_all(Local::now());
+                            if let Err(x) = playtime.save(&playtime_path) {
+                                error!("Failed to save playtime! - {}", x);
+                            }
+                        }
+                        set_status!(on_server_stopping(server_status));
+
+                        match server_status {
+                            ServerStatus::Stopping{ initiator: StopInitiator::Console, .. } => {
+                                send_discord(message_format::status("Server stopping (triggered from console)"));
+                                info!("Server stopping from console.");
+                            },
+                            _ => send_discord(message_format::status("Server is now stopping...")),
+                        }
+                    },
+
+                    // The process can take a while to exit after saving, there's no need to wait for it to tell Discord
+                    Ok(FromServerLog::ServerStopped) => {
+                        if let ServerStatus::Stopping{ rcon, initiator, .. } = &mut server_status {
+                            // A stop that cut the countdown short is reported once the process exits
+                            let countdown_running = rcon.as_mut().map(|rcon| matches!(rcon.try_wait(), Ok(None))).unwrap_or(false);
+                            if stop_announced || countdown_running {
+                                continue;
                             }
+
+                            stop_announced = true;
+                            send_discord(message_format::status(format!("Server stopped ({}).", initiator.describe()).as_str()));
+                            info!("Server stopped ({}).", initiator.describe());
+                        }
+                    },
+
+                    Ok(FromServerLog::StderrOutput { line }) => {
+                        if let ServerStatus::Starting{..} = server_status {
+                            startup_output = Some(format!("stderr: {}", line));
                         }
                     },
 
-                    Ok(FromServerLog::ServerError { exception, sender }) => {
-                        if matches!(server_status, ServerStatus::Running{..} | ServerStatus::Stopping{..}) {
-                            let now = Local::now();
-                            if now - last_error_reported >= ERROR_TIMEOUT {
-                                last_error_reported = now;
-                                send_discord(format!("Server encountered an exception:```md\n{}: {}```", sender, exception));
+                    Ok(FromServerLog::ServerError { exception, sender, time }) => {
+                        if let ServerStatus::Starting{..} = server_status {
+                            startup_error = Some(format!("{}: {}", sender, exception));
+                        }
+
+                        if config.relay.errors && (server_status.is_up() || matches!(server_status, ServerStatus::Stopping{..})) {
+                            if error_cache.is_empty() && time - last_error_reported >= ERROR_TIMEOUT {
+                                last_error_reported = time;
+                                send_discord(message_format::error("Server encountered an exception", format!("{}: {}", sender, exception).as_str()));
+                            } else {
+                                error_cache.push(format!("{}: {}", sender, exception));
                             }
                         }
                     },
 
                     Ok(FromServerLog::LagSpike { length, ticks }) => {
-                        send_discord(format!("Lag spike - {}ms, skipped {} ticks\nIf the problem persists, restart the server", length.num_milliseconds(), ticks));
+                        state!().lag_spikes += 1;
+
+                        if !config.relay.lag_spikes || !config.is_lag_reportable(length, ticks) {
+                            continue;
+                        }
+
+                        let now = Local::now();
+                        if lag_spike_cache.is_empty() && now - last_lag_reported >= config.lag_report_window() {
+                            last_lag_reported = now;
+                            send_discord(message_format::warning(format!("Lag spike - {}ms, skipped {} ticks\nIf the problem persists, restart the server", length.num_milliseconds(), ticks).as_str()));
+                        } else {
+                            lag_spike_cache.push(length);
+                        }
                     },
 
                     Ok(FromServerLog::BackupStarted) => {
-                        send_or_queue!("Server".to_string(), format!("*Backup started*"));
+                        backup_in_progress = true;
+                        if config.relay.backups {
+                            send_or_queue!("Server", format!("*Backup started*"));
+                        }
                     },
                     Ok(FromServerLog::BackupFinished { time }) => {
-                        send_or_queue!("Server".to_string(), format!("*Backup finished - {}s*", time.num_seconds()));
+                        backup_in_progress = false;
+
+                        state!().record_backup(Local::now(), time, BACKUP_HISTORY_LEN);
+
+                        if config.relay.backups {
+                            send_or_queue!("Server", format!("*Backup finished - {}s*", time.num_seconds()));
+                        }
+
+                        if let Some(sync_command) = config.backup_sync_command.clone() {
+                            let backup_folder = match config.backup_folder.as_deref() {
+                                Some(folder) => PathBuf::from(folder),
+                                None => Path::new(&config.server_folder).join("backups"),
+                            };
+
+                            match newest_file(&backup_folder) {
+                                Some(_) if backup_sync.is_some() => {
+                                    warn!("Previous backup sync still running, skipping this one");
+                                },
+                                Some(backup) => {
+                                    let sync_command = sync_command.replace("{backup}", &shell_quote(&backup.to_string_lossy()));
+                                    match shell_command(&sync_command).stdin(Stdio::null()).spawn() {
+                                        Ok(sync) => {
+                                            info!("Backup sync started: {}", sync_command);
+                                            backup_sync = Some(sync);
+                                        },
+                                        Err(x) => {
+                                            send_discord(message_format::warning("Backup sync failed to start"));
+                                            error!("Backup sync failed to start - {}", x);
+                                        }
+                                    }
+                                },
+                                None => {
+                                    send_discord(message_format::warning("Backup sync skipped, no backup found"));
+                                    warn!("No backup found in {}", backup_folder.display());
+                                }
+                            }
+                        }
                     },
 
                     Ok(FromServerLog::UserLogin { name }) => {
-                        send_or_queue!("Server".to_string(), format!("*{} joined the game*", name));
+                        if let (Some(template), true) = (&config.welcome_message, server_status.is_up()) {
+                            if greeting_allowed(&mut last_welcomed, &name, Local::now(), config.greeting_cooldown()) {
+                                let text = serde_json::json!({ "text": template.replace("{player}", &name) });
+                                rcon_in_background(&config, format!("tellraw {} {}", name, text));
+                            }
+                        }
+
+                        empty_since = None;
+                        let online = {
+                            let mut state = state!();
+                            state.online_players.insert(name.clone());
+                            state.online_players.len()
+                        };
+                        if online == 1 && config.announce_first_player {
+                            send_discord(message_format::status(format!("🎉 First player online: **{}**", name).as_str()));
+                        }
+                        playtime.login(&name, Local::now());
+                        if let Err(x) = playtime.save(&playtime_path) {
+                            error!("Failed to save playtime! - {}", x);
+                        }
+
+                        if player_stats.login(&name, online) {
+                            if let Err(x) = player_stats.save(&stats_path) {
+                                error!("Failed to save player stats! - {}", x);
+                            }
+                        }
+
+                        if config.relay.joins_leaves {
+                            queue_chat!(CachedChat::Join(message_format::escape_markdown(&config.display_name(&name))), Local::now());
+                        }
                     },
                     Ok(FromServerLog::UserLogout { name }) => {
-                        send_or_queue!("Server".to_string(), format!("*{} left the game*", name));
+                        if let (Some(template), true) = (&config.goodbye_message, server_status.is_up()) {
+                            if greeting_allowed(&mut last_goodbye, &name, Local::now(), config.greeting_cooldown()) {
+                                let text = serde_json::json!({ "text": template.replace("{player}", &name) });
+                                rcon_in_background(&config, format!("tellraw @a {}", text));
+                            }
+                        }
+
+                        let emptied = {
+                            let mut state = state!();
+                            state.online_players.remove(&name) && state.online_players.is_empty()
+                        };
+                        if emptied {
+                            empty_since = Some(Local::now());
+                            empty_reminder_sent = false;
+
+                            if config.announce_server_empty {
+                                send_discord(message_format::status("Server is now empty"));
+                            }
+                        }
+                        playtime.logout(&name, Local::now());
+                        if let Err(x) = playtime.save(&playtime_path) {
+                            error!("Failed to save playtime! - {}", x);
+                        }
+
+                        if config.relay.joins_leaves {
+                            queue_chat!(CachedChat::Leave(message_format::escape_markdown(&config.display_name(&name))), Local::now());
+                        }
                     },
 
-                    Ok(FromServerLog::ChatMessage { name, message }) => {
-                        send_or_queue!(name, message);
+                    Ok(FromServerLog::PlayerDeath { name, cause }) => {
+                        if config.relay.deaths {
+                            send_or_queue!("Server", format!("*{} {}*", message_format::escape_markdown(&config.display_name(&name)), message_format::escape_markdown(&cause)));
+                        }
+                    },
+
+                    Ok(FromServerLog::Advancement { name, verb, advancement }) => {
+                        if config.relay.advancements {
+                            send_or_queue!("Server", format!("*{} {} {}*", message_format::escape_markdown(&config.display_name(&name)), verb, message_format::escape_markdown(&advancement)));
+                        }
+                    },
+
+                    Ok(FromServerLog::PlayerKicked { name, reason }) => {
+                        if config.relay.moderation {
+                            send_or_queue!("Server", message_format::moderation(&message_format::escape_markdown(&config.display_name(&name)), "kicked", &message_format::escape_markdown(&reason)));
+                        }
+                    },
+
+                    Ok(FromServerLog::PlayerBanned { name, reason }) => {
+                        if config.relay.moderation {
+                            send_or_queue!("Server", message_format::moderation(&message_format::escape_markdown(&config.display_name(&name)), "banned", &message_format::escape_markdown(&reason)));
+                        }
+                    },
+
+                    Ok(FromServerLog::WhitelistRejected { name }) => {
+                        if config.relay.moderation {
+                            send_or_queue!("Server", format!("*{} tried to join, but isn't on the whitelist*", message_format::escape_markdown(&name)));
+                        }
+                    },
+
+                    Ok(FromServerLog::ChatMessage { name, message, time }) => {
+                        if config.relay.chat {
+                            // Players could otherwise ping @everyone or break the formatting of the feed
+                            send_or_queue!(message_format::escape_markdown(&config.display_name(&name)), message_format::escape_markdown(&message), time);
+                        }
+                    },
+
+                    Ok(FromServerLog::PipeClosed) => {
+                        let server_alive = match &mut server_status {
+                            ServerStatus::Running{ server, .. } | ServerStatus::Starting{ server, .. } => matches!(server.try_wait(), Ok(None)),
+                            _ => false
+                        };
+                        if !server_alive {
+                            continue;
+                        }
+
+                        if log_tail.is_some() {
+                            send_discord(message_format::warning("Lost the server log again, chat and player tracking are down until the next restart"));
+                            error!("Server log tail ended while the server is running.");
+                            continue;
+                        }
+
+                        let latest_log = server_log_file(&config);
+                        let active = Arc::new(AtomicBool::new(true));
+                        match LogTail::open(&latest_log, active.clone()) {
+                            Ok(tail) => {
+                                spawn_server_log_thread(&config, tail, server_log_send.clone());
+                                log_tail = Some(active);
+                                send_discord(message_format::warning("Lost the server output, following latest.log instead"));
+                                warn!("Server output closed, tailing {}", latest_log.display());
+                            },
+                            Err(x) => {
+                                send_discord(message_format::warning("Lost the server output, chat and player tracking are down until the next restart"));
+                                error!("Server output closed, could not open {} - {}", latest_log.display(), x);
+                            }
+                        }
                     },
 
                     Err(_) => {
@@ -526,7 +2117,8 @@ fn main_thread(config: &Value, bot: Discord) -> Result<(), Box<dyn Error>> {
                             error!("Server log pipe died, but server is not running or unknown");
                         } else {
                             error!("Server log pipe died, no idea about server status!");
-                            server_status = ServerStatus::Unknown;
+                            send_discord(message_format::warning("Lost connection to the server log, status may be unreliable"));
+                            set_status!(ServerStatus::Unknown);
                         }
 
                         let new_server_log = bounded::<FromServerLog>(5);
@@ -535,26 +2127,429 @@ fn main_thread(config: &Value, bot: Discord) -> Result<(), Box<dyn Error>> {
                     }
                 }
             }
-            recv(timeout) -> _ => { continue; }
+            recv(from_tps) -> tps => {
+                tps_poll_running = false;
+
+                let (tps, threshold) = match (tps, config.tps_alert_threshold()) {
+                    (Ok(Some(tps)), Some(threshold)) if server_status.is_up() => (tps, threshold),
+                    (Ok(None), _) => {
+                        debug!("Could not read TPS from `{}`", config.tps_command);
+                        continue;
+                    },
+                    _ => continue
+                };
+
+                tps_window.push(Local::now(), tps);
+                if !tps_warned && tps_window.is_sustained_low(threshold) {
+                    tps_warned = true;
+                    send_discord(message_format::warning(format!("TPS has been below {} for {}, averaging {:.1}\nIf the problem persists, restart the server",
+                        threshold, format_duration(config.tps_alert_window()), tps_window.average().unwrap_or(tps)).as_str()));
+                    warn!("Sustained low TPS, {}", tps);
+                } else if tps_warned && tps >= threshold {
+                    tps_warned = false;
+                    send_discord(message_format::status(format!("TPS recovered, now {:.1}", tps).as_str()));
+                }
+            },
+            recv(from_signal) -> _ => {
+                if exit_requested {
+                    warn!("Signalled again, killing the server.");
+                    if let ServerStatus::Starting{ server, .. } | ServerStatus::Running{ server, .. } | ServerStatus::Stopping{ server: Some(server), .. } = &mut server_status {
+                        server.kill().ok();
+                        server.wait().ok();
+                    }
+                    send_discord(message_format::status("Bot stopped, server killed."));
+                    return Ok(());
+                }
+                exit_requested = true;
+
+                set_status!(match server_status {
+                    ServerStatus::Running{ mut server, start_time } => {
+                        let rcon = match run_rcon(&config, &["say Server is going down with the bot", "shutdown"]) {
+                            Ok(rcon) => Some(rcon),
+                            Err(x) => {
+                                error!("Failed to stop the server through RCON, killing it! - {}", x);
+                                server.kill().ok();
+                                None
+                            }
+                        };
+                        ServerStatus::Stopping{ server: Some(server), start_time: Some(start_time), rcon, initiator: StopInitiator::BotExit }
+                    },
+                    // RCON isn't up yet
+                    ServerStatus::Starting{ mut server, .. } => {
+                        server.kill().ok();
+                        ServerStatus::Stopping{ server: Some(server), start_time: None, rcon: None, initiator: StopInitiator::BotExit }
+                    },
+                    // Skip what's left of a stop countdown
+                    ServerStatus::Stopping{ server: Some(server), start_time, rcon, .. } => {
+                        if let Some(mut countdown) = rcon {
+                            countdown.kill().ok();
+                            countdown.wait().ok();
+                        }
+                        let rcon = run_rcon(&config, &["shutdown"]).ok();
+                        ServerStatus::Stopping{ server: Some(server), start_time, rcon, initiator: StopInitiator::BotExit }
+                    },
+                    // Not our process, nothing to wait for
+                    ServerStatus::Stopping{ server: None, .. } => ServerStatus::Unknown,
+                    other => other
+                });
+
+                if matches!(server_status, ServerStatus::Stopping{..}) {
+                    send_discord(message_format::status("Bot is shutting down, waiting for the server to stop first"));
+                    info!("Signalled, waiting for the server to stop. Signal again to kill it.");
+                }
+            },
+            recv(timeout) -> _ => {
+                let now = Local::now();
+                let scheduled_shutdown = state!().scheduled_shutdown;
+                if let Some(offset) = restart_warning_due(scheduled_shutdown, &config.restart_warnings(), &mut restart_warned, now) {
+                    let warning = format!("Server will shut down in {}", format_duration(offset));
+                    send_discord(message_format::status(warning.as_str()));
+                    rcon_in_background(&config, format!("say {}", warning));
+                    info!("Shutdown warning sent, {} left.", format_duration(offset));
+                }
+
+                // A starting server is stopped once it's up
+                let server_starting = matches!(server_status, ServerStatus::Starting{..});
+                if !server_starting && shutdown_due(&mut state!().scheduled_shutdown, now) {
+                    restart_warned.clear();
+                    match server_status {
+                        ServerStatus::Running{ server, start_time } => match run_rcon(&config, &["say Scheduled shutdown", "shutdown"]) {
+                            Ok(rcon) => {
+                                set_status!(ServerStatus::Stopping{ server: Some(server), start_time: Some(start_time), rcon: Some(rcon), initiator: StopInitiator::Scheduled });
+                                send_discord(message_format::status("Scheduled shutdown, server is stopping now"));
+                                info!("Scheduled shutdown started.");
+                            },
+                            Err(x) => {
+                                set_status!(ServerStatus::Running{ server, start_time });
+                                send_discord(message_format::error("Scheduled shutdown failed", x.to_string().as_str()));
+                                error!("Scheduled shutdown failed! - {}", x);
+                            }
+                        },
+                        other => {
+                            let reason = shutdown_skip_reason(&other).unwrap_or_default();
+                            send_discord(message_format::status(format!("Scheduled shutdown skipped, {}", reason).as_str()));
+                            info!("Scheduled shutdown skipped, {}.", reason);
+                            set_status!(other);
+                        },
+                    }
+                }
+
+                if config.tps_alert_threshold().is_some() && server_status.is_up() {
+                    if !tps_poll_running && now - last_tps_poll >= config.tps_poll_interval() {
+                        tps_poll_running = true;
+                        last_tps_poll = now;
+
+                        let poll_config = config.clone();
+                        let tps_command = config.tps_command.clone();
+                        let tps_send = tps_send.clone();
+                        thread::spawn(move || {
+                            let tps = rcon_output(&poll_config, &[tps_command.as_str()]).ok()
+                                .and_then(|output| tps::parse_tps(output.as_str()));
+                            tps_send.send(tps).ok();
+                        });
+                    }
+                } else {
+                    tps_window.clear();
+                    tps_warned = false;
+                }
+
+                if let (Some(startup_timeout), ServerStatus::Starting{ server, start_time }) = (config.startup_timeout(), &mut server_status) {
+                    if !startup_warned && now - *start_time >= startup_timeout {
+                        startup_warned = true;
+
+                        if config.startup_timeout_kill {
+                            server.kill().ok();
+                            send_discord(message_format::warning(format!("Server did not start in {}, killed it", format_duration(startup_timeout)).as_str()));
+                            error!("Server startup timed out, killed.");
+                        } else {
+                            send_discord(message_format::warning(format!("Server is taking unusually long to start ({} so far), it may have hung", format_duration(now - *start_time)).as_str()));
+                            warn!("Server startup is taking unusually long.");
+                        }
+                    }
+                }
+
+                if !lag_spike_cache.is_empty() && now - last_lag_reported >= config.lag_report_window() {
+                    let worst = lag_spike_cache.iter().max().copied().unwrap_or_else(Duration::zero);
+                    send_discord(message_format::warning(format!("Lag spikes continued, worst {}ms in the last {}s ({} more spikes suppressed)\nIf the problem persists, restart the server",
+                        worst.num_milliseconds(), (now - last_lag_reported).num_seconds(), lag_spike_cache.len()).as_str()));
+
+                    last_lag_reported = now;
+                    lag_spike_cache.clear();
+                }
+
+                // Edits the same message, so the channel isn't flooded while nothing happens
+                if let Some(interval) = config.heartbeat_interval() {
+                    if now - last_heartbeat >= interval {
+                        last_heartbeat = now;
+
+                        let message_str = format!("Bot is alive, server is {} - last checked {}", server_status.name(), now.format("%Y-%m-%d %H:%M"));
+                        sender.send(Outgoing::Heartbeat { channel: bot_channel, text: message_str });
+                    }
+                }
+
+                // Without this, the tail of a chat burst would wait for the next message to go out
+                if !chat_msg_cache.is_empty() && now - last_chat_msg > MESSAGE_TIMEOUT {
+                    flush_chat!(now);
+                }
+
+                if !error_cache.is_empty() && now - last_error_reported >= ERROR_TIMEOUT {
+                    send_discord(message_format::error(format!("{} exceptions in the last {}s", error_cache.len(), (now - last_error_reported).num_seconds()).as_str(),
+                        message_format::error_summary(&error_cache).as_str()));
+
+                    last_error_reported = now;
+                    error_cache.clear();
+                }
+
+                if let (Some(reminder_timeout), Some(since), ServerStatus::Running{..}) = (config.empty_reminder_timeout(), empty_since, &server_status) {
+                    let empty_time = Local::now() - since;
+                    if !empty_reminder_sent && empty_time >= reminder_timeout {
+                        empty_reminder_sent = true;
+                        send_discord(message_format::warning(format!("Nobody has been online for {}, consider stopping the server with `{prefix}stop`",
+                            format_duration(empty_time), prefix = config.prefix).as_str()));
+                        info!("Sent empty server reminder.");
+                    }
+                }
+            }
         }
     }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let config: Value = {
-        let mut file = File::open("config.json")?;
-        let mut config_str = String::new();
-        file.read_to_string(&mut config_str)?;
+    let args = clap::App::new("server-maintainer")
+        .version(clap::crate_version!())
+        .arg(clap::Arg::with_name("config")
+            .long("config")
+            .value_name("PATH")
+            .default_value("config.json")
+            .help("Config file to use"))
+        .arg(clap::Arg::with_name("init")
+            .long("init")
+            .help("Interactively creates the config file, then exits"))
+        .arg(clap::Arg::with_name("dry-run")
+            .long("dry-run")
+            .alias("check")
+            .help("Checks the config file, then exits without connecting to Discord"))
+        .get_matches();
 
-        serde_json::from_str(config_str.as_str())?
-    };
+    let config_path = args.value_of("config").unwrap_or("config.json");
+
+    if args.is_present("init") {
+        return init_wizard::run_wizard(config_path);
+    }
+
+    let config = load_config(config_path)?;
+
+    let bot_channel = bot_channel(&config)?;
+
+    if args.is_present("dry-run") {
+        let mut problems = preflight::validate_config(&config);
+        problems.extend(preflight::preflight_check(&config));
+        for problem in problems.iter() {
+            eprintln!("{}", problem);
+        }
+
+        if !problems.is_empty() {
+            return Err(format!("{} problems found in {}", problems.len(), config_path).into());
+        }
+
+        println!("{} looks good", config_path);
+        return Ok(());
+    }
 
     setup_logger(&config)?;
+
+    let problems = preflight::validate_config(&config);
+    if !problems.is_empty() {
+        for problem in problems.iter() {
+            error!("Config: {}", problem);
+        }
+        return Err(format!("{} problems found in {}, not starting", problems.len(), config_path).into());
+    }
+
     let bot = create_discord_client(&config)?;
     info!("Started");
     
-    main_thread(&config, bot)?;
+    main_thread(config_path, &config, bot, bot_channel)?;
     
     info!("Stopping");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A harmless child process to stand in for the server or mcrcon.
+    fn spawn_dummy_child() -> Child {
+        Command::new(std::env::current_exe().unwrap())
+            .arg("--list")
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rcon_error_message() {
+        assert_eq!(rcon_error_message("Connection failed.\nError 111: Connection refused.\n", "exit status: 1"), "Connection failed. Error 111: Connection refused.");
+        assert_eq!(rcon_error_message("", "exit status: 1"), "mcrcon exited with exit status: 1");
+    }
+
+    #[test]
+    fn test_server_stopping_keeps_rcon() {
+        let rcon = spawn_dummy_child();
+        let rcon_id = rcon.id();
+
+        let server_status = on_server_stopping(ServerStatus::Stopping { server: None, start_time: None, rcon: Some(rcon), initiator: StopInitiator::Admin });
+
+        match server_status {
+            ServerStatus::Stopping { server: None, rcon: Some(mut rcon), initiator: StopInitiator::Admin, .. } => {
+                assert_eq!(rcon.id(), rcon_id);
+                rcon.wait().ok();
+            },
+            _ => panic!("Stopping state was clobbered")
+        }
+    }
+
+    #[test]
+    fn test_server_stopping_from_console() {
+        let server = spawn_dummy_child();
+
+        let start_time = Local::now();
+        let server_status = on_server_stopping(ServerStatus::Running { server, start_time });
+
+        match server_status {
+            ServerStatus::Stopping { server: Some(mut server), start_time: Some(stopping_start_time), rcon: None, initiator: StopInitiator::Console } => {
+                assert_eq!(stopping_start_time, start_time);
+                server.wait().ok();
+            },
+            _ => panic!("Console stop wasn't recognized")
+        }
+    }
+
+    #[test]
+    fn test_cancel_after_server_stopped() {
+        let spawn_exited = || {
+            let mut child = spawn_dummy_child();
+            child.wait().unwrap();
+            child
+        };
+
+        let server_status = ServerStatus::Stopping { server: Some(spawn_exited()), start_time: None, rcon: Some(spawn_exited()), initiator: StopInitiator::Admin };
+        let (server_status, outcome) = cancel_stop(server_status);
+
+        assert_eq!(outcome, CancelOutcome::TooLate);
+        assert!(matches!(server_status, ServerStatus::Stopping { server: Some(_), rcon: Some(_), .. }));
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("backups/world 2021.zip"), "'backups/world 2021.zip'");
+        assert_eq!(shell_quote("it's; rm -rf ~"), "'it'\\''s; rm -rf ~'");
+
+        let output = shell_command(&format!("printf %s {}", shell_quote("a'b $HOME; c"))).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a'b $HOME; c");
+    }
+
+    #[test]
+    fn test_greeting_cooldown() {
+        let mut last_greeted = HashMap::new();
+        let now = Local::now();
+
+        assert!(greeting_allowed(&mut last_greeted, "Kistepsi", now, Duration::minutes(10)));
+        assert!(!greeting_allowed(&mut last_greeted, "Kistepsi", now + Duration::minutes(1), Duration::minutes(10)));
+        assert!(greeting_allowed(&mut last_greeted, "Davidminer_MC", now + Duration::minutes(1), Duration::minutes(10)));
+        assert!(greeting_allowed(&mut last_greeted, "Kistepsi", now + Duration::minutes(11), Duration::minutes(10)));
+    }
+
+    #[test]
+    fn test_backup_rejected_while_running() {
+        assert!(check_backup_allowed(&ServerStatus::Unknown, false).is_ok());
+        assert_eq!(check_backup_allowed(&ServerStatus::Unknown, true), Err("A backup is already running"));
+    }
+
+    #[test]
+    fn test_require_running() {
+        assert!(require_running(&ServerStatus::Unknown).is_ok());
+        assert_eq!(require_running(&ServerStatus::Offline), Err("Server's not running (yet)"));
+        assert_eq!(require_running(&ServerStatus::Stopping { server: None, start_time: None, rcon: None, initiator: StopInitiator::Console }), Err("Server's stopping"));
+        assert!(require_running(&ServerStatus::Monitoring { up: true }).is_ok());
+        assert_eq!(require_running(&ServerStatus::Monitoring { up: false }), Err("Server's not running (yet)"));
+    }
+
+    #[test]
+    fn test_monitoring_status() {
+        assert!(ServerStatus::Monitoring { up: true }.is_up());
+        assert_eq!(ServerStatus::Monitoring { up: true }.name(), "running");
+        assert!(!ServerStatus::Monitoring { up: false }.is_up());
+        assert!(!ServerStatus::Unknown.is_up());
+    }
+
+    #[test]
+    fn test_backup_rejected_while_offline() {
+        assert_eq!(check_backup_allowed(&ServerStatus::Offline, false), Err("Server's not running (yet)"));
+        assert_eq!(check_backup_allowed(&ServerStatus::Offline, true), Err("Server's not running (yet)"));
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        use chrono::TimeZone;
+        let now = Local.ymd(2021, 6, 1).and_hms(12, 0, 0);
+
+        assert_eq!(next_occurrence(now, 18, 30), Some(Local.ymd(2021, 6, 1).and_hms(18, 30, 0)));
+        assert_eq!(next_occurrence(now, 6, 0), Some(Local.ymd(2021, 6, 2).and_hms(6, 0, 0)));
+        assert_eq!(next_occurrence(now, 12, 0), Some(Local.ymd(2021, 6, 2).and_hms(12, 0, 0)));
+    }
+
+    #[test]
+    fn test_cancelled_shutdown_does_not_fire() {
+        let now = Local::now();
+        let mut scheduled = Some(now + Duration::minutes(30));
+        assert!(!shutdown_due(&mut scheduled, now));
+
+        let mut warned = vec![Duration::minutes(15)];
+        let reply = cancel_scheduled_shutdown(&mut scheduled, &mut warned, "Kistepsi").unwrap();
+        assert!(reply.ends_with("cancelled by Kistepsi"));
+        assert!(warned.is_empty());
+        assert!(!shutdown_due(&mut scheduled, now + Duration::hours(1)));
+        assert_eq!(cancel_scheduled_shutdown(&mut scheduled, &mut warned, "Kistepsi"), None);
+
+        let mut scheduled = Some(now + Duration::minutes(30));
+        assert!(shutdown_due(&mut scheduled, now + Duration::minutes(30)));
+        assert_eq!(scheduled, None);
+    }
+
+    #[test]
+    fn test_shutdown_skip_reason() {
+        assert_eq!(shutdown_skip_reason(&ServerStatus::Offline), Some("the server isn't running"));
+        assert_eq!(shutdown_skip_reason(&ServerStatus::Unknown), Some("the server isn't running"));
+        assert_eq!(shutdown_skip_reason(&ServerStatus::Monitoring { up: true }), Some("the server wasn't started by the bot"));
+        assert_eq!(shutdown_skip_reason(&ServerStatus::Stopping { server: None, start_time: None, rcon: None, initiator: StopInitiator::Admin }),
+            Some("the server is already stopping"));
+
+        let status = ServerStatus::Running { server: spawn_dummy_child(), start_time: Local::now() };
+        assert_eq!(shutdown_skip_reason(&status), None);
+
+        if let ServerStatus::Running { mut server, .. } = status {
+            server.wait().ok();
+        }
+    }
+
+    #[test]
+    fn test_restart_warning_due() {
+        let now = Local::now();
+        let offsets = [Duration::minutes(15), Duration::minutes(5), Duration::minutes(1)];
+        let scheduled = Some(now + Duration::minutes(20));
+        let mut warned = Vec::new();
+
+        assert_eq!(restart_warning_due(scheduled, &offsets, &mut warned, now), None);
+        assert_eq!(restart_warning_due(scheduled, &offsets, &mut warned, now + Duration::minutes(5)), Some(Duration::minutes(15)));
+        assert_eq!(restart_warning_due(scheduled, &offsets, &mut warned, now + Duration::minutes(6)), None);
+
+        // A late tick only sends the closest of the missed warnings
+        assert_eq!(restart_warning_due(scheduled, &offsets, &mut warned, now + Duration::minutes(19)), Some(Duration::minutes(1)));
+        assert_eq!(restart_warning_due(scheduled, &offsets, &mut warned, now + Duration::minutes(19)), None);
+
+        assert_eq!(restart_warning_due(None, &offsets, &mut Vec::new(), now), None);
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt::Display;
+
+/// A line of the chat feed, player chat or a server event like a join.
+pub fn chat_line(name: impl Display, message: impl Display) -> String {
+    format!("<**{}**> {}", name, message)
+}
+
+/// Escapes Discord markdown in text from players, and breaks up mentions like `@everyone` with a zero-width space.
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' | '*' | '_' | '~' | '`' | '|' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '@' => escaped += "@\u{200B}",
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// A player removed by a moderator, eg. `*Steve was kicked: Spamming*`.
+pub fn moderation(name: &str, action: &str, reason: &str) -> String {
+    if reason.is_empty() {
+        format!("*{} was {}*", name, action)
+    } else {
+        format!("*{} was {}: {}*", name, action, reason)
+    }
+}
+
+/// A server status transition, eg. starting or stopped.
+pub fn status(message: &str) -> String {
+    format!("🔹 *{}*", message)
+}
+
+/// Something that needs attention but isn't broken, eg. lag.
+pub fn warning(message: &str) -> String {
+    format!("⚠️ {}", message)
+}
+
+/// An error, with the details in a code block.
+pub fn error(message: &str, details: &str) -> String {
+    format!("❌ {}:```md\n{}```", message, details)
+}
+
+/// Removes `§` formatting codes, or turns bold and italic into Discord markdown if `markdown` is set.
+/// Like in game, a color code or `§r` ends the formatting before it.
+pub fn minecraft_formatting(text: &str, markdown: bool) -> String {
+    let mut formatted = String::new();
+    let mut open = Vec::<&str>::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '§' {
+            formatted.push(c);
+            continue;
+        }
+
+        match chars.next().map(|code| code.to_ascii_lowercase()) {
+            Some(code @ 'l') | Some(code @ 'o') if markdown => {
+                let marker = if code == 'l' { "**" } else { "*" };
+                if !open.contains(&marker) {
+                    formatted += marker;
+                    open.push(marker);
+                }
+            },
+            Some('0'..='9') | Some('a'..='f') | Some('r') => {
+                while let Some(marker) = open.pop() {
+                    formatted += marker;
+                }
+            },
+            _ => ()
+        }
+    }
+
+    while let Some(marker) = open.pop() {
+        formatted += marker;
+    }
+
+    formatted
+}
+
+/// A Discord message as a line of in-game chat.
+/// Newlines and other control characters would split or break the RCON command, and `§` would format it, so they're dropped.
+pub fn game_chat_line(author: &str, content: &str) -> String {
+    const MAX_CHAT_LEN: usize = 256;
+
+    let clean = |text: &str| -> String {
+        minecraft_formatting(text, false)
+            .split(|c: char| c.is_control() || c.is_whitespace())
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    format!("[Discord] <{}> {}", clean(author), clean(content))
+        .chars()
+        .take(MAX_CHAT_LEN)
+        .collect()
+}
+
+/// The RCON command showing a Discord message to everyone in game.
+/// `tellraw` instead of `say`, since the server logs `say` as `[Server]` chat, which would be relayed back to Discord.
+/// The text goes in as JSON, so quotes and backslashes in it are escaped.
+pub fn game_chat_command(author: &str, content: &str) -> String {
+    format!("tellraw @a {}", serde_json::json!({ "text": game_chat_line(author, content) }))
+}
+
+/// Joins player names for a single line, eg. `Steve, Alex, and 3 others`, listing at most `shown` of them.
+pub fn name_list(names: &[String], shown: usize) -> String {
+    match names {
+        [] => String::new(),
+        [name] => name.clone(),
+        [first, second] => format!("{} and {}", first, second),
+        _ if names.len() > shown + 1 => format!("{}, and {} others", names[..shown].join(", "), names.len() - shown),
+        [rest @ .., last] => format!("{}, and {}", rest.join(", "), last),
+    }
+}
+
+/// Lists the distinct errors in the order they were first seen, with a count for repeats.
+/// Only the first few are listed, so the summary fits in one Discord message.
+pub fn error_summary(errors: &[String]) -> String {
+    const MAX_LISTED: usize = 5;
+    const MAX_LINE_LEN: usize = 300;
+
+    let mut distinct = Vec::<(&str, usize)>::new();
+    for error in errors {
+        match distinct.iter_mut().find(|(seen, _)| seen == error) {
+            Some((_, count)) => *count += 1,
+            None => distinct.push((error, 1)),
+        }
+    }
+
+    let mut lines: Vec<String> = distinct.iter()
+        .take(MAX_LISTED)
+        .map(|(error, count)| {
+            let error: String = error.chars().take(MAX_LINE_LEN).collect();
+            if *count > 1 { format!("{} (x{})", error, count) } else { error }
+        })
+        .collect();
+
+    if distinct.len() > MAX_LISTED {
+        lines.push(format!("...and {} more", distinct.len() - MAX_LISTED));
+    }
+
+    lines.join("\n")
+}
+
+/// Splits long output into code blocks that each fit in a Discord message.
+pub fn code_block_chunks(text: &str) -> Vec<String> {
+    const MAX_CHUNK_LEN: usize = 1900;
+
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+
+    for line in text.lines() {
+        if !chunk.is_empty() && chunk.len() + line.len() + 1 > MAX_CHUNK_LEN {
+            chunks.push(format!("```\n{}```", chunk));
+            chunk.clear();
+        }
+
+        let mut line = line;
+        while line.len() > MAX_CHUNK_LEN {
+            let mut split = MAX_CHUNK_LEN;
+            while !line.is_char_boundary(split) {
+                split -= 1;
+            }
+
+            chunks.push(format!("```\n{}```", &line[..split]));
+            line = &line[split..];
+        }
+
+        chunk += line;
+        chunk.push('\n');
+    }
+
+    if !chunk.trim().is_empty() {
+        chunks.push(format!("```\n{}```", chunk));
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(chat_line("Kistepsi", "nem"), "<**Kistepsi**> nem");
+        assert_eq!(status("Server stopped."), "🔹 *Server stopped.*");
+        assert_eq!(moderation("Kistepsi", "kicked", ""), "*Kistepsi was kicked*");
+        assert_eq!(moderation("Kistepsi", "banned", "Griefing"), "*Kistepsi was banned: Griefing*");
+        assert_eq!(error("Server encountered an exception", "Server thread: oops"), "❌ Server encountered an exception:```md\nServer thread: oops```");
+    }
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(escape_markdown("__Kiste*psi__"), "\\_\\_Kiste\\*psi\\_\\_");
+        assert_eq!(escape_markdown("hi @everyone <@&1234>"), "hi @\u{200B}everyone <@\u{200B}&1234>");
+        assert_eq!(escape_markdown("`code` ~~strike~~ ||spoiler||"), "\\`code\\` \\~\\~strike\\~\\~ \\|\\|spoiler\\|\\|");
+        assert_eq!(escape_markdown("§lbold"), "§lbold");
+    }
+
+    #[test]
+    fn test_name_list() {
+        let names: Vec<String> = ["Steve", "Alex", "Kistepsi", "szmarci07iq", "Davidminer_MC"].iter().map(|x| x.to_string()).collect();
+
+        assert_eq!(name_list(&names[..1], 2), "Steve");
+        assert_eq!(name_list(&names[..2], 2), "Steve and Alex");
+        assert_eq!(name_list(&names[..3], 2), "Steve, Alex, and Kistepsi");
+        assert_eq!(name_list(&names[..4], 2), "Steve, Alex, and 2 others");
+        assert_eq!(name_list(&names, 2), "Steve, Alex, and 3 others");
+        assert_eq!(name_list(&names, 5), "Steve, Alex, Kistepsi, szmarci07iq, and Davidminer_MC");
+    }
+
+    #[test]
+    fn test_minecraft_formatting() {
+        let text = "§6§lServer§r restarting §oin §l5§r §kminutes§z";
+
+        assert_eq!(minecraft_formatting(text, false), "Server restarting in 5 minutes");
+        assert_eq!(minecraft_formatting(text, true), "**Server** restarting *in **5*** minutes");
+        assert_eq!(minecraft_formatting("§lunclosed", true), "**unclosed**");
+        assert_eq!(minecraft_formatting("trailing §", false), "trailing ");
+    }
+
+    #[test]
+    fn test_game_chat_line() {
+        assert_eq!(game_chat_line("Kistepsi", "hello\nthere  §4red"), "[Discord] <Kistepsi> hello there red");
+        assert_eq!(game_chat_line("Kistepsi", &"a".repeat(300)).chars().count(), 256);
+    }
+
+    #[test]
+    fn test_game_chat_command() {
+        assert_eq!(game_chat_command("Kistepsi", "hello"), r#"tellraw @a {"text":"[Discord] <Kistepsi> hello"}"#);
+        assert_eq!(game_chat_command("Kistepsi", r#"say "hi" \o/"#), r#"tellraw @a {"text":"[Discord] <Kistepsi> say \"hi\" \\o/"}"#);
+    }
+
+    #[test]
+    fn test_error_summary() {
+        let errors: Vec<String> = vec!["Server thread: oops", "Worker: boom", "Server thread: oops"].into_iter().map(String::from).collect();
+        assert_eq!(error_summary(&errors), "Server thread: oops (x2)\nWorker: boom");
+
+        let errors: Vec<String> = (0..7).map(|x| format!("error {}", x)).collect();
+        assert!(error_summary(&errors).ends_with("error 4\n...and 2 more"));
+    }
+
+    #[test]
+    fn test_code_block_chunks() {
+        assert_eq!(code_block_chunks("There are 0 of a max of 20 players online: "), vec!["```\nThere are 0 of a max of 20 players online: \n```"]);
+        assert!(code_block_chunks("").is_empty());
+
+        let long_output = "a".repeat(1000) + "\n" + &"b".repeat(1000) + "\n" + &"c".repeat(4000);
+        let chunks = code_block_chunks(long_output.as_str());
+        assert_eq!(chunks.len(), 5);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 2000));
+    }
+}
@@ -0,0 +1,144 @@
+use std::error::Error;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use chrono::{DateTime, Local};
+use log::*;
+
+use crate::server_state::{ServerState, SharedState};
+
+const STATES: [&str; 5] = ["unknown", "offline", "starting", "running", "stopping"];
+
+/// Renders the state in the Prometheus text exposition format.
+pub fn render(state: &ServerState, now: DateTime<Local>) -> String {
+    let mut output = String::new();
+
+    output += "# HELP server_maintainer_info Version of the bot.\n";
+    output += "# TYPE server_maintainer_info gauge\n";
+    output += format!("server_maintainer_info{{version=\"{}\"}} 1\n", state.bot_version).as_str();
+
+    if let Some(version) = &state.version {
+        output += "# HELP minecraft_server_info Version of the server.\n";
+        output += "# TYPE minecraft_server_info gauge\n";
+        output += format!("minecraft_server_info{{version=\"{}\"}} 1\n", version).as_str();
+    }
+
+    output += "# HELP minecraft_server_status Current state of the server.\n";
+    output += "# TYPE minecraft_server_status gauge\n";
+    for status in STATES.iter() {
+        output += format!("minecraft_server_status{{state=\"{}\"}} {}\n", status, (*status == state.status) as u8).as_str();
+    }
+
+    output += "# HELP minecraft_players_online Players currently online.\n";
+    output += "# TYPE minecraft_players_online gauge\n";
+    output += format!("minecraft_players_online {}\n", state.online_players.len()).as_str();
+
+    if let Some(max_players) = state.max_players {
+        output += "# HELP minecraft_players_max Player limit from server.properties.\n";
+        output += "# TYPE minecraft_players_max gauge\n";
+        output += format!("minecraft_players_max {}\n", max_players).as_str();
+    }
+
+    output += "# HELP minecraft_uptime_seconds Seconds since the server was started.\n";
+    output += "# TYPE minecraft_uptime_seconds gauge\n";
+    output += format!("minecraft_uptime_seconds {}\n", state.uptime(now).map(|x| x.num_seconds()).unwrap_or_default()).as_str();
+
+    if let Some(time) = state.scheduled_shutdown {
+        output += "# HELP minecraft_scheduled_shutdown_timestamp_seconds When the scheduled shutdown happens.\n";
+        output += "# TYPE minecraft_scheduled_shutdown_timestamp_seconds gauge\n";
+        output += format!("minecraft_scheduled_shutdown_timestamp_seconds {}\n", time.timestamp()).as_str();
+    }
+
+    output += "# HELP minecraft_lag_spikes_total Lag spikes logged by the server.\n";
+    output += "# TYPE minecraft_lag_spikes_total counter\n";
+    output += format!("minecraft_lag_spikes_total {}\n", state.lag_spikes).as_str();
+
+    output += "# HELP minecraft_backups_total Backups finished.\n";
+    output += "# TYPE minecraft_backups_total counter\n";
+    output += format!("minecraft_backups_total {}\n", state.backups).as_str();
+
+    if let Some((_, duration)) = state.last_backup() {
+        output += "# HELP minecraft_last_backup_duration_seconds Duration of the last backup.\n";
+        output += "# TYPE minecraft_last_backup_duration_seconds gauge\n";
+        output += format!("minecraft_last_backup_duration_seconds {}\n", duration.num_seconds()).as_str();
+    }
+
+    output
+}
+
+fn handle_connection(stream: TcpStream, state: &SharedState) -> Result<(), Box<dyn Error>> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = match path {
+        "/metrics" => ("200 OK", render(&*state.read().map_err(|_| "Server state lock poisoned")?, Local::now())),
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    };
+
+    let mut stream = stream;
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body)?;
+
+    Ok(())
+}
+
+/// Serves `/metrics` over HTTP on its own thread.
+pub fn spawn_metrics_server(address: &str, state: SharedState) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(address)?;
+    info!("Serving metrics on http://{}/metrics", address);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.map_err(Box::from)
+                .and_then(|stream| {
+                    stream.set_read_timeout(Some(std::time::Duration::from_secs(5)))?;
+                    handle_connection(stream, &state)
+                });
+
+            if let Err(x) = result {
+                debug!("Metrics request failed - {}", x);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_render() {
+        let now = Local::now();
+        let state = ServerState {
+            status: "running",
+            online_players: ["Kistepsi", "Davidminer_MC", ".Steve"].iter().map(|name| name.to_string()).collect(),
+            running_since: Some(now - Duration::seconds(90)),
+            lag_spikes: 2,
+            backups: 1,
+            backup_history: vec![(now - Duration::hours(1), Duration::seconds(30)), (now, Duration::seconds(42))].into(),
+            max_players: Some(20),
+            version: Some("1.16.5".to_string()),
+            scheduled_shutdown: Some(now + Duration::minutes(30)),
+            bot_version: "0.2.0",
+        };
+        let output = render(&state, now);
+
+        assert!(output.contains("minecraft_server_status{state=\"running\"} 1\n"));
+        assert!(output.contains("minecraft_server_status{state=\"offline\"} 0\n"));
+        assert!(output.contains("minecraft_players_online 3\n"));
+        assert!(output.contains("minecraft_players_max 20\n"));
+        assert!(output.contains("server_maintainer_info{version=\"0.2.0\"} 1\n"));
+        assert!(output.contains("minecraft_server_info{version=\"1.16.5\"} 1\n"));
+        assert!(output.contains(format!("minecraft_scheduled_shutdown_timestamp_seconds {}\n", (now + Duration::minutes(30)).timestamp()).as_str()));
+        assert!(output.contains("minecraft_uptime_seconds 90\n"));
+        assert!(output.contains("minecraft_lag_spikes_total 2\n"));
+        assert!(output.contains("minecraft_backups_total 1\n"));
+        assert!(output.contains("minecraft_last_backup_duration_seconds 42\n"));
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::prelude::*;
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::message_format::minecraft_formatting;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub peak_players: usize,
+    pub unique_players: BTreeSet<String>,
+
+    #[serde(skip)]
+    pub session_peak: usize,
+    #[serde(skip)]
+    pub session_unique: BTreeSet<String>,
+}
+
+impl PlayerStats {
+    /// Loads the cumulative stats, starting from scratch if the file is missing or broken.
+    pub fn load(path: &str) -> PlayerStats {
+        let read_stats = || -> Result<PlayerStats, Box<dyn Error>> {
+            let mut file = File::open(path)?;
+            let mut stats_str = String::new();
+            file.read_to_string(&mut stats_str)?;
+
+            Ok(serde_json::from_str(stats_str.as_str())?)
+        };
+
+        match read_stats() {
+            Ok(stats) => stats,
+            Err(x) => {
+                warn!("Could not load player stats from {}, starting fresh - {}", path, x);
+                PlayerStats::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Records a login with the current online count.
+    /// Returns whether the cumulative stats changed and should be saved.
+    pub fn login(&mut self, name: &str, online_count: usize) -> bool {
+        self.session_peak = self.session_peak.max(online_count);
+        self.session_unique.insert(name.to_string());
+
+        let mut changed = self.unique_players.insert(name.to_string());
+        if online_count > self.peak_players {
+            self.peak_players = online_count;
+            changed = true;
+        }
+
+        changed
+    }
+
+    pub fn reset_session(&mut self) {
+        self.session_peak = 0;
+        self.session_unique.clear();
+    }
+}
+
+/// The answer to the `list` command.
+#[derive(Debug, Eq, PartialEq)]
+pub struct PlayerList {
+    pub online: usize,
+    pub max: usize,
+    pub names: Vec<String>,
+}
+
+/// Parses the output of `list`, eg. `There are 2 of a max of 20 players online: Kistepsi, Davidminer_MC`,
+/// or `There are 2/20 players online:` with the names on the next line on older servers.
+pub fn parse_player_list(output: &str) -> Option<PlayerList> {
+    let output = minecraft_formatting(output, false);
+    let mut lines = output.lines().skip_while(|line| !line.contains("There are "));
+
+    let line = lines.next()?;
+    let counts = &line[line.find("There are ")?..];
+    let (counts, names) = match counts.find(':') {
+        Some(colon) => (&counts[..colon], counts[colon + 1..].trim()),
+        None => (counts, ""),
+    };
+
+    let mut numbers = counts.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|x| x.parse().ok());
+    let online = numbers.next()?;
+    let max = numbers.next()?;
+
+    if online == 0 {
+        return Some(PlayerList { online, max, names: Vec::new() });
+    }
+
+    // Older servers put the names on the next line
+    let names = if names.is_empty() { lines.next().unwrap_or_default().trim() } else { names };
+    let names = names.split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+        .collect();
+
+    Some(PlayerList { online, max, names })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_login_tracks_peak_and_unique() {
+        let mut stats = PlayerStats::default();
+
+        assert!(stats.login("Kistepsi", 1));
+        assert!(stats.login("Davidminer_MC", 2));
+        assert!(!stats.login("Kistepsi", 2));
+
+        assert_eq!(stats.session_peak, 2);
+        assert_eq!(stats.session_unique.len(), 2);
+        assert_eq!(stats.peak_players, 2);
+        assert_eq!(stats.unique_players.len(), 2);
+    }
+
+    #[test]
+    fn test_reset_session_keeps_cumulative() {
+        let mut stats = PlayerStats::default();
+        stats.login("Kistepsi", 1);
+        stats.login("Davidminer_MC", 2);
+
+        stats.reset_session();
+        assert!(!stats.login("Kistepsi", 1));
+
+        assert_eq!(stats.session_peak, 1);
+        assert_eq!(stats.session_unique.len(), 1);
+        assert_eq!(stats.peak_players, 2);
+        assert_eq!(stats.unique_players.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_player_list() {
+        let modern = "There are 2 of a max of 20 players online: Kistepsi, Davidminer_MC";
+        assert_eq!(parse_player_list(modern), Some(PlayerList {
+            online: 2,
+            max: 20,
+            names: vec!["Kistepsi".to_string(), "Davidminer_MC".to_string()],
+        }));
+
+        let legacy = "There are 1/20 players online:\nszmarci07iq";
+        assert_eq!(parse_player_list(legacy).unwrap().names, vec!["szmarci07iq".to_string()]);
+
+        let empty = "§6There are §c0§6 out of maximum §c20§6 players online.";
+        assert_eq!(parse_player_list(empty), Some(PlayerList { online: 0, max: 20, names: Vec::new() }));
+
+        assert_eq!(parse_player_list("Unknown command"), None);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::prelude::*;
+
+use chrono::{DateTime, Duration, Local};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+/// Per-player playtime, in seconds, plus the join timestamps of ongoing sessions.
+/// Join timestamps are persisted too, so a bot restart mid-session doesn't lose time.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Playtime {
+    pub total_secs: BTreeMap<String, i64>,
+    pub sessions: BTreeMap<String, i64>,
+}
+
+impl Playtime {
+    /// Loads the playtime file, starting from scratch if it is missing or broken.
+    pub fn load(path: &str) -> Playtime {
+        let read_playtime = || -> Result<Playtime, Box<dyn Error>> {
+            let mut file = File::open(path)?;
+            let mut playtime_str = String::new();
+            file.read_to_string(&mut playtime_str)?;
+
+            Ok(serde_json::from_str(playtime_str.as_str())?)
+        };
+
+        match read_playtime() {
+            Ok(playtime) => playtime,
+            Err(x) => {
+                warn!("Could not load playtime from {}, starting fresh - {}", path, x);
+                Playtime::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn login(&mut self, name: &str, now: DateTime<Local>) {
+        self.sessions.entry(name.to_string()).or_insert_with(|| now.timestamp());
+    }
+
+    pub fn logout(&mut self, name: &str, now: DateTime<Local>) {
+        if let Some(join_time) = self.sessions.remove(name) {
+            let elapsed = (now.timestamp() - join_time).max(0);
+            *self.total_secs.entry(name.to_string()).or_default() += elapsed;
+        }
+    }
+
+    /// Ends every ongoing session, eg. when the server stops.
+    pub fn logout_all(&mut self, now: DateTime<Local>) {
+        let names: Vec<String> = self.sessions.keys().cloned().collect();
+        for name in names {
+            self.logout(&name, now);
+        }
+    }
+
+    /// Drops sessions left open by a server that stopped while we weren't watching.
+    /// Their end time is unknown, so they aren't counted at all.
+    pub fn discard_sessions(&mut self) -> usize {
+        let count = self.sessions.len();
+        self.sessions.clear();
+        count
+    }
+
+    /// Total playtime of a player, including their ongoing session.
+    pub fn total(&self, name: &str, now: DateTime<Local>) -> Option<Duration> {
+        let total = self.total_secs.get(name).copied();
+        let ongoing = self.sessions.get(name).map(|join_time| (now.timestamp() - join_time).max(0));
+
+        if total.is_none() && ongoing.is_none() {
+            return None;
+        }
+
+        Some(Duration::seconds(total.unwrap_or_default() + ongoing.unwrap_or_default()))
+    }
+
+    /// Players ordered by total playtime, longest first.
+    pub fn leaderboard(&self, now: DateTime<Local>) -> Vec<(String, Duration)> {
+        let mut board: Vec<(String, Duration)> = self.total_secs.keys()
+            .chain(self.sessions.keys())
+            .filter_map(|name| self.total(name, now).map(|time| (name.clone(), time)))
+            .collect();
+
+        board.sort_by(|(name_a, time_a), (name_b, time_b)| time_b.cmp(time_a).then(name_a.cmp(name_b)));
+        board.dedup_by(|(name_a, _), (name_b, _)| name_a == name_b);
+
+        board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playtime_counts_ongoing_session() {
+        let start = Local::now();
+        let mut playtime = Playtime::default();
+
+        playtime.login("Kistepsi", start);
+        playtime.logout("Kistepsi", start + Duration::minutes(30));
+        playtime.login("Kistepsi", start + Duration::minutes(60));
+
+        let total = playtime.total("Kistepsi", start + Duration::minutes(75));
+        assert_eq!(total, Some(Duration::minutes(45)));
+        assert_eq!(playtime.total("Davidminer_MC", start), None);
+    }
+
+    #[test]
+    fn test_leaderboard_order() {
+        let start = Local::now();
+        let mut playtime = Playtime::default();
+
+        playtime.login("Kistepsi", start);
+        playtime.login("Davidminer_MC", start);
+        playtime.logout("Kistepsi", start + Duration::minutes(10));
+
+        let board = playtime.leaderboard(start + Duration::minutes(20));
+        assert_eq!(board, vec![
+            ("Davidminer_MC".to_string(), Duration::minutes(20)),
+            ("Kistepsi".to_string(), Duration::minutes(10)),
+        ]);
+    }
+}
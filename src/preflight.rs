@@ -0,0 +1,168 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::jvm_flags::parse_ram_mb;
+
+/// Finds a binary either at its path, or by name on the `PATH`.
+fn find_binary(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.components().count() > 1 {
+        return Some(path.to_path_buf()).filter(|path| path.is_file());
+    }
+
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .flat_map(|dir| vec![dir.join(name), dir.join(format!("{}.exe", name))])
+            .find(|path| path.is_file())
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata().map(|meta| meta.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Checks that the keys the bot needs are present and well formed, returning a description of each problem found.
+/// Unlike `preflight_check`, these are fatal - the bot would fail later on with a less useful error.
+pub fn validate_config(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let has_key = |value: &Option<String>| value.as_deref().filter(|x| !x.is_empty()).is_some();
+
+    let has_login = has_key(&config.token) || (has_key(&config.username) && has_key(&config.password));
+    if !has_login {
+        problems.push("No token in config file".to_string());
+    }
+
+    if config.channel_id == 0 {
+        problems.push("channel_id in config file must be a numeric channel ID".to_string());
+    }
+
+    let mut required = vec![
+        ("server-folder", &config.server_folder),
+        ("mcrcon-path", &config.mcrcon_path),
+        ("rcon_password", &config.rcon_password),
+    ];
+    if config.managed {
+        required.push(("java-path", &config.java_path));
+        required.push(("server-path", &config.server_path));
+    }
+    for (name, value) in required {
+        if value.is_empty() {
+            problems.push(format!("No {} in config file", name));
+        }
+    }
+
+    if config.managed {
+        let mut ram = Vec::new();
+        for (name, amount) in &[("min-ram", &config.min_ram), ("max-ram", &config.max_ram)] {
+            match parse_ram_mb(amount) {
+                _ if amount.is_empty() => problems.push(format!("No {} in config file", name)),
+                Some(mb) if mb > 0 => ram.push(mb),
+                _ => problems.push(format!("{} ({}) should be an amount like 512M or 4G", name, amount)),
+            }
+        }
+
+        if let [min_ram, max_ram] = ram[..] {
+            if min_ram > max_ram {
+                problems.push("min-ram is larger than max-ram".to_string());
+            }
+        }
+    }
+
+    problems
+}
+
+/// Checks the configured paths and binaries, returning a description of each problem found.
+pub fn preflight_check(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // An externally managed server is started by someone else, Java and the jar aren't needed
+    let mut binaries = vec![("mcrcon-path", &config.mcrcon_path)];
+    if config.managed {
+        binaries.insert(0, ("java-path", &config.java_path));
+    }
+
+    for (name, binary) in binaries {
+        match find_binary(binary) {
+            _ if binary.is_empty() => problems.push(format!("No {} in config file", name)),
+            Some(path) if is_executable(&path) => (),
+            Some(_) => problems.push(format!("{} ({}) is not executable", name, binary)),
+            None => problems.push(format!("{} ({}) was not found", name, binary)),
+        }
+    }
+
+    let jar = &config.server_path;
+    if config.managed && jar.is_empty() {
+        problems.push("No server-path in config file".to_string());
+    } else if config.managed && !Path::new(jar).is_file() {
+        problems.push(format!("server-path ({}) does not exist", jar));
+    }
+
+    let folder = &config.server_folder;
+    if folder.is_empty() {
+        problems.push("No server-folder in config file".to_string());
+    } else if !Path::new(folder).is_dir() {
+        problems.push(format!("server-folder ({}) is not a directory", folder));
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_config() {
+        let config = serde_json::json!({
+            "token": "abc",
+            "channel_id": 123,
+            "server-folder": "server",
+            "mcrcon-path": "mcrcon",
+            "rcon_password": "pass",
+            "java-path": "java",
+            "server-path": "server.jar",
+            "min-ram": "2G",
+            "max-ram": "4096m",
+        });
+        assert!(validate_config(&Config::from_value(&config).unwrap()).is_empty());
+
+        let config = serde_json::json!({
+            "username": "bot",
+            "password": "pass",
+            "channel_id": 123,
+            "server-folder": "server",
+            "mcrcon-path": "mcrcon",
+            "rcon_password": "pass",
+            "java-path": "java",
+            "server-path": "server.jar",
+            "min-ram": "4",
+            "max-ram": "4G",
+        });
+        assert_eq!(validate_config(&Config::from_value(&config).unwrap()), vec!["min-ram (4) should be an amount like 512M or 4G"]);
+
+        let config = serde_json::json!({ "min-ram": "8G", "max-ram": "4G" });
+        let problems = validate_config(&Config::from_value(&config).unwrap());
+        assert!(problems.contains(&"No token in config file".to_string()));
+        assert!(problems.contains(&"No java-path in config file".to_string()));
+        assert!(problems.contains(&"min-ram is larger than max-ram".to_string()));
+
+        let config = serde_json::json!({
+            "token": "abc",
+            "managed": false,
+            "channel_id": 123,
+            "server-folder": "server",
+            "mcrcon-path": "mcrcon",
+            "rcon_password": "pass",
+        });
+        assert!(validate_config(&Config::from_value(&config).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+/// Token bucket for outgoing Discord messages.
+/// Messages over the limit wait for their turn instead of being dropped.
+pub struct RateLimiter {
+    capacity: f64,
+    per_message: Duration,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Allows `messages` messages every `interval`, with bursts of up to `messages`.
+    pub fn new(messages: u32, interval: Duration, now: Instant) -> RateLimiter {
+        let capacity = f64::from(messages.max(1));
+
+        RateLimiter {
+            capacity,
+            per_message: interval.div_f64(capacity),
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    /// Takes a token, returning how long to wait before sending.
+    pub fn acquire(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() / self.per_message.as_secs_f64()).min(self.capacity);
+        self.last_refill = now;
+
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            self.per_message.mul_f64(-self.tokens)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_wait() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(5, Duration::from_secs(5), now);
+
+        for _ in 0..5 {
+            assert_eq!(limiter.acquire(now), Duration::from_secs(0));
+        }
+        assert_eq!(limiter.acquire(now), Duration::from_secs(1));
+        assert_eq!(limiter.acquire(now), Duration::from_secs(2));
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(limiter.acquire(later), Duration::from_secs(0));
+    }
+}
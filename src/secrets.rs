@@ -0,0 +1,67 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+
+use serde_json::Value;
+
+/// Config keys that may hold `env:VAR_NAME` or `file:/path` references instead of the secret itself.
+const SECRET_KEYS: [&str; 3] = ["password", "rcon_password", "token"];
+
+fn resolve_secret(key: &str, value: &str) -> Result<String, Box<dyn Error>> {
+    if let Some(var) = value.strip_prefix("env:") {
+        return env::var(var)
+            .map_err(|x| format!("{} refers to environment variable {} - {}", key, var, x).into());
+    }
+
+    if let Some(path) = value.strip_prefix("file:") {
+        return fs::read_to_string(path)
+            .map(|secret| secret.trim_end_matches(&['\n', '\r'][..]).to_string())
+            .map_err(|x| format!("{} refers to file {} - {}", key, path, x).into());
+    }
+
+    Ok(value.to_string())
+}
+
+/// Replaces secret references in the config with the secrets they point to.
+pub fn resolve_secrets(config: &mut Value) -> Result<(), Box<dyn Error>> {
+    for key in SECRET_KEYS.iter() {
+        if let Some(Value::String(value)) = config.get_mut(*key) {
+            *value = resolve_secret(key, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_secrets() {
+        env::set_var("SERVER_MAINTAINER_TEST_RCON", "hunter2");
+        let secret_path = env::temp_dir().join("server_maintainer_test_password");
+        fs::write(&secret_path, "correct horse\n").unwrap();
+
+        let mut config = serde_json::json!({
+            "username": "env:USER",
+            "password": format!("file:{}", secret_path.display()),
+            "rcon_password": "env:SERVER_MAINTAINER_TEST_RCON",
+        });
+        resolve_secrets(&mut config).unwrap();
+        fs::remove_file(&secret_path).ok();
+
+        assert_eq!(config["username"], "env:USER");
+        assert_eq!(config["password"], "correct horse");
+        assert_eq!(config["rcon_password"], "hunter2");
+    }
+
+    #[test]
+    fn test_resolve_secrets_missing() {
+        let mut config = serde_json::json!({
+            "rcon_password": "env:SERVER_MAINTAINER_TEST_MISSING",
+        });
+
+        assert!(resolve_secrets(&mut config).is_err());
+    }
+}
@@ -1,23 +1,32 @@
 use std::error::Error;
-use std::io::prelude::*;
-use std::io::BufReader;
-use std::process::{ChildStdout};
-use std::sync::atomic::Ordering;
-
-use chrono::Duration;
+use std::fs::{self, File};
+use std::io::{self, prelude::*, BufReader, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use chrono::{DateTime, Duration, Local};
 use crossbeam::channel::Sender;
 use log::*;
-use serde_json::Value;
 
 use crate::CONSOLE_ENABLED;
+use crate::config::Config;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum FromServerLog {
+    /// Logged early in startup, eg. `1.20.1`.
+    ServerVersion {
+        version: String
+    },
     ServerStarted,
     ServerStopping,
+    /// The worlds are saved, the server process exits right after.
+    ServerStopped,
     ServerError {
         exception: String,
-        sender: String
+        sender: String,
+        time: DateTime<Local>
     },
     LagSpike {
         length: Duration,
@@ -35,11 +44,126 @@ pub enum FromServerLog {
     UserLogout {
         name: String
     },
+    PlayerDeath {
+        name: String,
+        cause: String
+    },
+    /// `verb` is how the server phrased it, eg. `has completed the challenge`.
+    Advancement {
+        name: String,
+        verb: String,
+        advancement: String
+    },
+    PlayerKicked {
+        name: String,
+        reason: String
+    },
+    PlayerBanned {
+        name: String,
+        reason: String
+    },
+    WhitelistRejected {
+        name: String
+    },
 
     ChatMessage {
         name: String,
-        message: String
+        message: String,
+        time: DateTime<Local>
     },
+
+    /// A stderr line that isn't a recognised failure, like JVM warnings.
+    StderrOutput {
+        line: String
+    },
+
+    /// The log being read ended, sent by the thread reading it.
+    PipeClosed,
+}
+
+/// Follows a log file like `tail -f`, for when the server's stdout is lost.
+/// Reads end once `active` is cleared.
+pub struct LogTail {
+    path: PathBuf,
+    /// `None` until the log exists, a fresh server only creates it once it starts.
+    file: Option<File>,
+    position: u64,
+    active: Arc<AtomicBool>,
+}
+
+impl LogTail {
+    /// Opens the log at its end, so only new lines are read.
+    /// If there's no log yet, it's read from the start once it shows up.
+    pub fn open(path: &Path, active: Arc<AtomicBool>) -> io::Result<LogTail> {
+        let (file, position) = match File::open(path) {
+            Ok(mut file) => {
+                let position = file.seek(SeekFrom::End(0))?;
+                (Some(file), position)
+            },
+            Err(x) if x.kind() == io::ErrorKind::NotFound => (None, 0),
+            Err(x) => return Err(x),
+        };
+
+        Ok(LogTail { path: path.to_path_buf(), file, position, active })
+    }
+
+    /// Whether the file at the path was replaced or truncated since it was opened.
+    fn rotated(&self) -> bool {
+        let meta = match fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            Err(_) => return false,
+        };
+
+        if meta.len() < self.position {
+            return true;
+        }
+
+        // A new log that's already longer than the old one can only be told apart by its inode
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if let Some(Ok(current)) = self.file.as_ref().map(File::metadata) {
+                return current.ino() != meta.ino() || current.dev() != meta.dev();
+            }
+        }
+
+        false
+    }
+}
+
+impl Read for LogTail {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.active.load(Ordering::Relaxed) {
+            let file = match &mut self.file {
+                Some(file) => file,
+                None => match File::open(&self.path) {
+                    Ok(file) => self.file.insert(file),
+                    Err(x) if x.kind() == io::ErrorKind::NotFound => {
+                        thread::sleep(std::time::Duration::from_millis(500));
+                        continue;
+                    },
+                    Err(x) => return Err(x),
+                },
+            };
+
+            let read = file.read(buf)?;
+            if read > 0 {
+                self.position += read as u64;
+                return Ok(read);
+            }
+
+            // The server starts a new latest.log when it rotates logs
+            if self.rotated() {
+                self.file = Some(File::open(&self.path)?);
+                self.position = 0;
+                continue;
+            }
+
+            thread::sleep(std::time::Duration::from_millis(500));
+        }
+
+        Ok(0)
+    }
 }
 
 
@@ -47,7 +171,10 @@ pub mod scanners {
     use std::error::Error;
     use text_io::try_scan;
     use super::FromServerLog;
-    use chrono::{Duration};
+    use chrono::{DateTime, Duration, Local, NaiveTime};
+    use serde_json::Value;
+
+    use crate::config::{Config, StartTriggerConfig};
 
     #[derive(Debug, Default, Eq, PartialEq)]
     pub struct ScannedLine {
@@ -93,18 +220,31 @@ pub mod scanners {
     }
 
     pub fn scan_line(line: &str) -> Result<ScannedLine, Box<dyn Error>> {
+        /// Splits the first `<name>`-style sender off the message.
+        /// Names may contain anything except the closing bracket, messages may contain anything.
+        fn split_sender(message: &str, open: char, close: char) -> Option<(String, String)> {
+            let message = message.strip_prefix(open)?;
+            let (name, rest) = message.split_at(message.find(close)?);
+            let rest = rest[close.len_utf8()..].strip_prefix(' ')?;
+
+            if name.is_empty() || name.starts_with(char::is_whitespace) {
+                return None;
+            }
+
+            Some((name.to_string(), rest.to_string()))
+        }
+
         fn scan_msg(line: &str) -> Result<ScannedLine, Box<dyn Error>> {
-            let mut scanned_line = ScannedLine::default();
-            let _temp: String;
+            let mut scanned_line = scan_log(line)?;
 
-            try_scan!(bytes_endl!(line) => "[{}] [{}/{}] [{}]: <{}> {}\n", 
-                scanned_line.time_str,
-                scanned_line.sender_thread,
-                scanned_line.level,
-                _temp,
-                scanned_line.sender_handle,
-                scanned_line.message
-            );
+            // Unsigned chat, eg. from Bedrock players bridged by Geyser, is marked on newer servers.
+            let chat = scanned_line.message.as_str();
+            let chat = chat.strip_prefix("[Not Secure] ").unwrap_or(chat);
+
+            let (name, message) = split_sender(chat, '<', '>')
+                .ok_or("Not a chat message")?;
+            scanned_line.sender_handle = name;
+            scanned_line.message = message;
 
             scanned_line.is_chat_msg = true;
 
@@ -112,17 +252,12 @@ pub mod scanners {
         }
 
         fn scan_console_msg(line: &str) -> Result<ScannedLine, Box<dyn Error>> {
-            let mut scanned_line = ScannedLine::default();
-            let _temp: String;
+            let mut scanned_line = scan_log(line)?;
 
-            try_scan!(bytes_endl!(line) => "[{}] [{}/{}] [{}]: [{}] {}\n", 
-                scanned_line.time_str,
-                scanned_line.sender_thread,
-                scanned_line.level,
-                _temp,
-                scanned_line.sender_handle,
-                scanned_line.message
-            );
+            let (name, message) = split_sender(scanned_line.message.as_str(), '[', ']')
+                .ok_or("Not a console message")?;
+            scanned_line.sender_handle = name;
+            scanned_line.message = message;
 
             scanned_line.is_chat_msg = 
                 scanned_line.sender_handle == "Server" 
@@ -169,12 +304,104 @@ pub mod scanners {
         Ok(scanned_line)
     }
 
+    /// Places the log's `hh:mm:ss` timestamp on the day it was most likely logged.
+    /// A time later than `now` was logged before midnight, so it is put on the previous day.
+    pub fn parse_log_time(time_str: &str, now: DateTime<Local>) -> Result<DateTime<Local>, Box<dyn Error>> {
+        let time = NaiveTime::parse_from_str(time_str, "%H:%M:%S")?;
+        let log_time = now.date().and_time(time).ok_or("Invalid local time")?;
+
+        if log_time > now + Duration::minutes(1) {
+            Ok(log_time - Duration::days(1))
+        } else {
+            Ok(log_time)
+        }
+    }
+
     simple_scan!(
         scan_server_start => "mcjtylib_ng": "RFTools: server is starting" -> FromServerLog::ServerStarted;
-        scan_server_stop => "minecraft/DedicatedServer": "Stopping the server" -> FromServerLog::ServerStopping;
         scan_backup_start => "minecraft/DedicatedServer": "Server Backup started!" -> FromServerLog::BackupStarted
     );
 
+    /// `Starting minecraft server version 1.20.1`, logged before the worlds load.
+    pub fn scan_server_version(sender: &str, message: &str) -> Result<(FromServerLog, String), Box<dyn Error>> {
+        if !sender.ends_with("DedicatedServer") {
+            return Err("Not the right username".into());
+        }
+
+        let version = message.strip_prefix("Starting minecraft server version ")
+            .map(str::trim)
+            .filter(|version| !version.is_empty())
+            .ok_or("Not a version message")?;
+
+        Ok((FromServerLog::ServerVersion { version: version.to_string() }, version.to_string()))
+    }
+
+    /// The vanilla startup line, eg. `Done (12.345s)! For help, type "help"`, for servers without RFTools.
+    /// Older versions add `or "?"` to the end.
+    pub fn scan_server_start_vanilla(sender: &str, message: &str) -> Result<FromServerLog, Box<dyn Error>> {
+        if !sender.ends_with("DedicatedServer") {
+            return Err("Not the right username".into());
+        }
+
+        let _time: String;
+        let _help: String;
+        try_scan!(bytes_endl!(message) => "Done ({})! For help, type {}\n", _time, _help);
+
+        Ok(FromServerLog::ServerStarted)
+    }
+
+    /// The `start_trigger` from the config, for modpacks that announce they're up some other way.
+    /// A string matches anywhere in a message, `{ "sender": ..., "message": ... }` also needs the sender to match exactly.
+    pub struct StartTrigger {
+        sender: Option<String>,
+        message: String,
+    }
+
+    impl StartTrigger {
+        pub fn from_config(config: &Config) -> Option<StartTrigger> {
+            match config.start_trigger.clone()? {
+                StartTriggerConfig::Message(message) => Some(StartTrigger { sender: None, message }),
+                StartTriggerConfig::Sender { sender, message } => Some(StartTrigger { sender, message }),
+            }
+        }
+
+        pub fn scan(&self, sender: &str, message: &str) -> Result<FromServerLog, Box<dyn Error>> {
+            if matches!(&self.sender, Some(trigger_sender) if trigger_sender != sender) {
+                return Err("Not the right username".into());
+            }
+
+            if message.contains(self.message.as_str()) {
+                Ok(FromServerLog::ServerStarted)
+            } else {
+                Err("Not the right message".into())
+            }
+        }
+    }
+
+    /// Forge logs `Stopping the server`, vanilla logs `Stopping server`.
+    pub fn scan_server_stop(sender: &str, message: &str) -> Result<FromServerLog, Box<dyn Error>> {
+        match (sender, message) {
+            ("minecraft/DedicatedServer", "Stopping the server")
+            | ("net.minecraft.server.MinecraftServer", "Stopping server")
+            | ("minecraft/MinecraftServer", "Stopping server") => Ok(FromServerLog::ServerStopping),
+            _ => Err("Not the right message".into())
+        }
+    }
+
+    /// The last line of a clean stop once every world is saved, before the process exits, eg.
+    /// `ThreadedAnvilChunkStorage: All dimensions are saved` on 1.16+.
+    pub fn scan_server_stopped(sender: &str, message: &str) -> Result<FromServerLog, Box<dyn Error>> {
+        if !sender.ends_with("MinecraftServer") {
+            return Err("Not the right username".into());
+        }
+
+        match message {
+            "ThreadedAnvilChunkStorage: All dimensions are saved"
+            | "ThreadedAnvilChunkStorage: All dimensions are saved." => Ok(FromServerLog::ServerStopped),
+            _ => Err("Not the right message".into())
+        }
+    }
+
     pub fn scan_backup_stop(sender: &str, message: &str) -> Result<(FromServerLog, Duration), Box<dyn Error>> {
         check_sender!(sender, "minecraft/DedicatedServer");
 
@@ -188,18 +415,119 @@ pub mod scanners {
         Ok((FromServerLog::BackupFinished { time }, time))
     }
 
+    /// Matches the "Can't keep up!" warning across server versions, eg.
+    /// `Running 5125ms behind, skipping 102 tick(s)` and `Running 5125ms or 102 ticks behind`.
     pub fn scan_lag_spike(sender: &str, message: &str) -> Result<(FromServerLog, Duration), Box<dyn Error>> {
-        check_sender!(sender, "minecraft/MinecraftServer");
+        if !sender.ends_with("MinecraftServer") {
+            return Err("Not the right username".into());
+        }
 
+        if !message.starts_with("Can't keep up!") {
+            return Err("Not the right message".into());
+        }
+
+        let running = message.find("Running ").ok_or("No lag length")?;
         let num: i64;
-        let ticks: usize;
+        try_scan!(bytes_endl!(&message[running..]) => "Running {}ms", num);
 
-        try_scan!(bytes_endl!(message) => "Can't keep up! Did the system time change, or is the server overloaded? Running {}ms behind, skipping {} tick(s)\n", num, ticks);
+        let ticks: usize;
+        if let Some(skipping) = message.find("skipping ") {
+            try_scan!(bytes_endl!(&message[skipping..]) => "skipping {} tick", ticks);
+        } else if let Some(ticks_or) = message.find("ms or ") {
+            try_scan!(bytes_endl!(&message[ticks_or..]) => "ms or {} tick", ticks);
+        } else {
+            ticks = 0;
+        }
 
         let length = Duration::milliseconds(num);
         Ok((FromServerLog::LagSpike { length, ticks }, length))
     }
 
+    fn component_text(component: &Value, text: &mut String) {
+        match component {
+            Value::String(part) => *text += part,
+            Value::Array(parts) => parts.iter().for_each(|part| component_text(part, text)),
+            Value::Object(object) => {
+                if let Some(Value::String(part)) = object.get("text") {
+                    *text += part;
+                }
+                if let Some(extra) = object.get("extra") {
+                    component_text(extra, text);
+                }
+            },
+            _ => ()
+        }
+    }
+
+    const STDERR_FAILURES: [&str; 6] = [
+        "Error:",
+        "Exception",
+        "OutOfMemoryError",
+        "Could not find or load main class",
+        "Unable to access jarfile",
+        "Error occurred during initialization of VM",
+    ];
+
+    /// Picks the JVM and launch failures out of stderr, the rest are warnings like `WARNING: An illegal reflective access`.
+    pub fn scan_stderr_failure(line: &str) -> Result<FromServerLog, Box<dyn Error>> {
+        let line = line.trim();
+        if line.starts_with("WARNING") || !STDERR_FAILURES.iter().any(|failure| line.contains(failure)) {
+            return Err("Not a failure".into());
+        }
+
+        Ok(FromServerLog::ServerError {
+            exception: line.to_string(),
+            sender: "stderr".to_string(),
+            time: Local::now()
+        })
+    }
+
+    /// Whether the JSON is a chat component, and not some other JSON a plugin logged.
+    fn is_text_component(component: &Value) -> bool {
+        match component {
+            Value::Object(object) => ["text", "extra", "translate"].iter().any(|key| object.contains_key(*key)),
+            Value::Array(parts) => !parts.is_empty() && parts.iter().all(|part| part.is_string() || is_text_component(part)),
+            _ => false
+        }
+    }
+
+    /// Extracts the plain text of a JSON chat line, eg. a plugin broadcast through `tellraw`.
+    /// Only the server logs these, JSON from other senders is left to the other scanners.
+    pub fn scan_json_chat(sender: &str, message: &str) -> Result<String, Box<dyn Error>> {
+        if !["minecraft/MinecraftServer", "minecraft/DedicatedServer", "Server"].contains(&sender) {
+            return Err("Not logged by the server".into());
+        }
+
+        if !message.starts_with('{') && !message.starts_with('[') {
+            return Err("Not a JSON message".into());
+        }
+
+        let component: Value = serde_json::from_str(message)?;
+        if !is_text_component(&component) {
+            return Err("Not a chat component".into());
+        }
+
+        let mut text = String::new();
+        component_text(&component, &mut text);
+
+        let text = crate::message_format::minecraft_formatting(text.as_str(), false).trim().to_string();
+        if text.is_empty() {
+            return Err("Empty JSON message".into());
+        }
+
+        Ok(text)
+    }
+
+    /// Strips the prefix that Floodgate puts in front of Bedrock player names, eg. `.Steve`.
+    /// Returns `None` for Java players.
+    pub fn strip_bedrock_prefix<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+        if prefix.is_empty() {
+            return None;
+        }
+
+        name.strip_prefix(prefix).filter(|name| !name.is_empty())
+    }
+
     pub fn scan_user_login(sender: &str, message: &str) -> Result<(FromServerLog, String), Box<dyn Error>> {
         check_sender!(sender, "minecraft/DedicatedServer");
 
@@ -220,6 +548,108 @@ pub mod scanners {
         Ok((FromServerLog::UserLogout { name: name.clone() }, name))
     }
 
+    /// The start of vanilla death messages after the player's name, eg. `fell from a high place`.
+    const DEATH_CAUSES: [&str; 34] = [
+        "was slain by", "was shot by", "was fireballed by", "was pummeled by", "was killed",
+        "was blown up by", "blew up", "was squashed", "was squished", "was impaled",
+        "was skewered", "was stung to death", "was poked to death", "was pricked to death",
+        "was struck by lightning", "was roasted in dragon", "was obliterated", "was burnt to a crisp",
+        "was frozen to death", "froze to death", "fell", "hit the ground too hard", "was doomed to fall",
+        "drowned", "burned to death", "went up in flames", "walked into", "tried to swim in lava",
+        "discovered the floor was lava", "suffocated", "starved to death", "withered away",
+        "experienced kinetic energy", "died",
+    ];
+
+    pub fn scan_player_death(sender: &str, message: &str) -> Result<(FromServerLog, String, String), Box<dyn Error>> {
+        check_sender!(sender, "minecraft/DedicatedServer");
+
+        let (name, cause) = message.split_at(message.find(' ').ok_or("Not a death message")?);
+        let cause = cause.trim_start();
+
+        if name.is_empty() || name.starts_with('[') || name.starts_with('<') {
+            return Err("Not a player name".into());
+        }
+
+        let is_death = DEATH_CAUSES.iter().any(|death| {
+            cause.strip_prefix(death).map(|rest| rest.is_empty() || rest.starts_with(' ') || rest.starts_with('\'')).unwrap_or(false)
+        });
+        if !is_death {
+            return Err("Not a death message".into());
+        }
+
+        Ok((FromServerLog::PlayerDeath { name: name.to_string(), cause: cause.to_string() }, name.to_string(), cause.to_string()))
+    }
+
+    const ADVANCEMENT_VERBS: [&str; 3] = ["has made the advancement", "has completed the challenge", "has reached the goal"];
+
+    /// Vanilla advancement announcements, eg. `Kistepsi has made the advancement [Stone Age]`.
+    pub fn scan_advancement(sender: &str, message: &str) -> Result<(FromServerLog, String, String), Box<dyn Error>> {
+        check_sender!(sender, "minecraft/DedicatedServer");
+
+        let (name, rest) = message.split_once(' ').ok_or("Not an advancement message")?;
+        if name.is_empty() || name.starts_with('[') || name.starts_with('<') {
+            return Err("Not a player name".into());
+        }
+
+        let (verb, advancement) = ADVANCEMENT_VERBS.iter()
+            .find_map(|verb| Some((*verb, rest.strip_prefix(verb)?.strip_prefix(' ')?)))
+            .ok_or("Not an advancement message")?;
+        if !advancement.starts_with('[') || !advancement.ends_with(']') {
+            return Err("Not an advancement message".into());
+        }
+
+        Ok((FromServerLog::Advancement { name: name.to_string(), verb: verb.to_string(), advancement: advancement.to_string() },
+            name.to_string(), advancement.to_string()))
+    }
+
+    /// Splits `Kicked Steve: reason` into the name and the reason, which may be missing.
+    /// Commands from a player or RCON are logged in brackets, eg. `[Rcon: Kicked Steve: reason]`.
+    fn scan_moderation<'a>(message: &'a str, action: &str) -> Option<(&'a str, &'a str)> {
+        let message = match message.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            Some(message) => message.split_once(": ")?.1,
+            None => message,
+        };
+
+        let rest = message.strip_prefix(action)?.strip_prefix(' ')?;
+        let (name, reason) = rest.split_once(": ").unwrap_or((rest, ""));
+        if name.is_empty() || name.contains(' ') {
+            return None;
+        }
+
+        Some((name, reason.trim()))
+    }
+
+    pub fn scan_player_kick(sender: &str, message: &str) -> Result<(FromServerLog, String), Box<dyn Error>> {
+        check_sender!(sender, "minecraft/DedicatedServer");
+
+        let (name, reason) = scan_moderation(message, "Kicked").ok_or("Not a kick message")?;
+        Ok((FromServerLog::PlayerKicked { name: name.to_string(), reason: reason.to_string() }, name.to_string()))
+    }
+
+    pub fn scan_player_ban(sender: &str, message: &str) -> Result<(FromServerLog, String), Box<dyn Error>> {
+        check_sender!(sender, "minecraft/DedicatedServer");
+
+        let (name, reason) = scan_moderation(message, "Banned").ok_or("Not a ban message")?;
+        Ok((FromServerLog::PlayerBanned { name: name.to_string(), reason: reason.to_string() }, name.to_string()))
+    }
+
+    /// A login refused by the whitelist, either with the whole profile
+    /// `Disconnecting com.mojang.authlib.GameProfile@1b2c3d[id=<null>,name=Steve,properties={},legacy=false] (/127.0.0.1:51234): You are not white-listed on this server!`
+    /// or just the name on newer versions. The sender differs between versions, so it isn't checked.
+    pub fn scan_whitelist_rejection(_sender: &str, message: &str) -> Result<(FromServerLog, String), Box<dyn Error>> {
+        let rest = message.strip_prefix("Disconnecting ").ok_or("Not a disconnect message")?;
+        if !rest.ends_with("You are not white-listed on this server!") && !rest.ends_with("You are not whitelisted on this server!") {
+            return Err("Not a whitelist rejection".into());
+        }
+
+        let name = match rest.find("name=") {
+            Some(start) => rest[start + "name=".len()..].split(&[',', ']'][..]).next(),
+            None => rest.split(' ').next(),
+        }.filter(|name| !name.is_empty()).ok_or("No player name")?;
+
+        Ok((FromServerLog::WhitelistRejected { name: name.to_string() }, name.to_string()))
+    }
+
     #[cfg(test)]
     mod tests {
         /// [21:07:11] [Server thread/INFO] [minecraft/DedicatedServer]: <Kistepsi> nem
@@ -242,6 +672,46 @@ pub mod scanners {
             assert_eq!(result.unwrap(), scan_option);
         }
     
+        /// [21:07:11] [Server thread/INFO] [minecraft/DedicatedServer]: <Ákos.ö_1> hi <3 -> you >_<
+        #[test]
+        fn test_scan_line_chat_unicode() {
+            use super::*;
+
+            let scan_msg = r#"[21:07:11] [Server thread/INFO] [minecraft/DedicatedServer]: <Ákos.ö_1> hi <3 -> you >_<"#;
+            let scan_option = ScannedLine {
+                time_str: "21:07:11".to_string(),
+                sender_thread: "Server thread".to_string(),
+                level: "INFO".to_string(),
+                sender_handle: "Ákos.ö_1".to_string(),
+                is_chat_msg: true,
+                message: "hi <3 -> you >_<".to_string(),
+            };
+            let result = scan_line(scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), scan_option);
+        }
+
+        /// [21:07:11] [Server thread/INFO] [minecraft/DedicatedServer]: <Some Bedrock Guy> <not a name> message
+        #[test]
+        fn test_scan_line_chat_spaces_brackets() {
+            use super::*;
+
+            let scan_msg = r#"[21:07:11] [Server thread/INFO] [minecraft/DedicatedServer]: <Some Bedrock Guy> <not a name> message"#;
+            let scan_option = ScannedLine {
+                time_str: "21:07:11".to_string(),
+                sender_thread: "Server thread".to_string(),
+                level: "INFO".to_string(),
+                sender_handle: "Some Bedrock Guy".to_string(),
+                is_chat_msg: true,
+                message: "<not a name> message".to_string(),
+            };
+            let result = scan_line(scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), scan_option);
+        }
+
         /// [21:31:06] [Server thread/INFO] [minecraft/DedicatedServer]: [Server] sdgfhljkjhlkdsfglkjhgfd sdgfhljkjhlkdsfglkjhgfd
         #[test]
         fn test_scan_line_server() {
@@ -322,6 +792,44 @@ pub mod scanners {
             assert_eq!(result.unwrap(), scan_option);
         }
 
+        /// [21:07:11]
+        #[test]
+        fn test_parse_log_time() {
+            use super::*;
+            use chrono::TimeZone;
+
+            let now = Local.ymd(2020, 11, 14).and_hms(21, 10, 0);
+            let expected_time = Local.ymd(2020, 11, 14).and_hms(21, 7, 11);
+
+            let result = parse_log_time("21:07:11", now);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), expected_time);
+        }
+
+        /// [23:59:58], processed after midnight
+        #[test]
+        fn test_parse_log_time_midnight() {
+            use super::*;
+            use chrono::TimeZone;
+
+            let now = Local.ymd(2020, 11, 15).and_hms(0, 0, 3);
+            let expected_time = Local.ymd(2020, 11, 14).and_hms(23, 59, 58);
+
+            let result = parse_log_time("23:59:58", now);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), expected_time);
+        }
+
+        #[test]
+        fn test_parse_log_time_invalid() {
+            use super::*;
+
+            assert!(parse_log_time("21:07", Local::now()).is_err());
+            assert!(parse_log_time("25:07:11", Local::now()).is_err());
+        }
+
         /// [minecraft/MinecraftServer]: Can't keep up! Did the system time change, or is the server overloaded? Running 5125ms behind, skipping 102 tick(s)
         #[test]
         fn test_scan_lag_spike() {
@@ -342,6 +850,26 @@ pub mod scanners {
             assert_eq!(result.unwrap(), (expected_msg, expected_time));
         }
 
+        /// [net.minecraft.server.MinecraftServer]: Can't keep up! Is the server overloaded? Running 2042ms or 40 ticks behind
+        #[test]
+        fn test_scan_lag_spike_new_wording() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "net.minecraft.server.MinecraftServer";
+            let scan_msg = r#"Can't keep up! Is the server overloaded? Running 2042ms or 40 ticks behind"#;
+            let expected_time = Duration::milliseconds(2042);
+            let expected_msg = FromServerLog::LagSpike {
+                length: expected_time,
+                ticks: 40
+            };
+
+            let result = scan_lag_spike(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), (expected_msg, expected_time));
+        }
+
         /// [mcjtylib_ng]: RFTools: server is starting
         #[test]
         fn test_scan_server_start() {
@@ -358,6 +886,69 @@ pub mod scanners {
             assert_eq!(result.unwrap(), expected_msg);
         }
 
+        /// [minecraft/DedicatedServer]: Done (12.345s)! For help, type "help"
+        #[test]
+        fn test_scan_server_start_vanilla() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let scan_msg = r#"Done (12.345s)! For help, type "help""#;
+            let expected_msg = FromServerLog::ServerStarted;
+
+            let result = scan_server_start_vanilla(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), expected_msg);
+
+            let old_msg = r#"Done (4.021s)! For help, type "help" or "?""#;
+            assert!(scan_server_start_vanilla("net.minecraft.server.dedicated.DedicatedServer", old_msg).is_ok());
+            assert!(scan_server_start_vanilla("mcjtylib_ng", scan_msg).is_err());
+            assert!(scan_server_start_vanilla(scan_sender, "Preparing spawn area: 83%").is_err());
+        }
+
+        /// [minecraft/DedicatedServer]: Starting minecraft server version 1.12.2
+        #[test]
+        fn test_scan_server_version() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let scan_msg = r#"Starting minecraft server version 1.12.2"#;
+
+            let expected_version = "1.12.2".to_string();
+            let expected_msg = FromServerLog::ServerVersion { version: expected_version.clone() };
+
+            let result = scan_server_version(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), (expected_msg, expected_version));
+
+            let result = scan_server_version("net.minecraft.server.dedicated.DedicatedServer", "Starting minecraft server version 1.20.1");
+            assert_eq!(result.unwrap().1, "1.20.1");
+            assert!(scan_server_version(scan_sender, "Starting minecraft server on *:25565").is_err());
+            assert!(scan_server_version("mcjtylib_ng", scan_msg).is_err());
+        }
+
+        #[test]
+        fn test_start_trigger() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            assert!(StartTrigger::from_config(&Config::from_value(&serde_json::json!({})).unwrap()).is_none());
+            assert!(StartTrigger::from_config(&Config::from_value(&serde_json::json!({ "start_trigger": "" })).unwrap()).is_none());
+
+            let trigger = StartTrigger::from_config(&Config::from_value(&serde_json::json!({ "start_trigger": "Loaded all modules" })).unwrap()).unwrap();
+            assert_eq!(trigger.scan("anything", "[Pack] Loaded all modules in 3s").unwrap(), FromServerLog::ServerStarted);
+            assert!(trigger.scan("anything", "Loading modules").is_err());
+
+            let trigger = StartTrigger::from_config(&Config::from_value(&serde_json::json!({
+                "start_trigger": { "sender": "minecraft/DedicatedServer", "message": "Done" }
+            })).unwrap()).unwrap();
+            assert!(trigger.scan("minecraft/DedicatedServer", "Done (12.345s)! For help, type \"help\"").is_ok());
+            assert!(trigger.scan("mcjtylib_ng", "Done").is_err());
+        }
+
         /// [net.minecraft.server.MinecraftServer]: Stopping server
         #[test]
         fn test_scan_server_stop() {
@@ -374,6 +965,48 @@ pub mod scanners {
             assert_eq!(result.unwrap(), expected_msg);
         }
 
+        /// [minecraft/DedicatedServer]: Stopping the server
+        #[test]
+        fn test_scan_server_stop_forge() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let scan_msg = r#"Stopping the server"#;
+            let expected_msg = FromServerLog::ServerStopping;
+
+            let result = scan_server_stop(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), expected_msg);
+        }
+
+        /// [minecraft/MinecraftServer]: ThreadedAnvilChunkStorage: All dimensions are saved
+        #[test]
+        fn test_scan_server_stopped() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/MinecraftServer";
+            let scan_msg = r#"ThreadedAnvilChunkStorage: All dimensions are saved"#;
+            let expected_msg = FromServerLog::ServerStopped;
+
+            let result = scan_server_stopped(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), expected_msg);
+        }
+
+        /// [minecraft/MinecraftServer]: ThreadedAnvilChunkStorage (world): All chunks are saved
+        #[test]
+        fn test_scan_server_stopped_single_dimension() {
+            use super::*;
+
+            assert!(scan_server_stopped("minecraft/MinecraftServer", "ThreadedAnvilChunkStorage (world): All chunks are saved").is_err());
+            assert!(scan_server_stopped("minecraft/MinecraftServer", "Saving worlds").is_err());
+            assert!(scan_server_stopped("minecraft/DedicatedServer", "ThreadedAnvilChunkStorage: All dimensions are saved").is_err());
+        }
+
         /// [minecraft/DedicatedServer]: Server Backup started!
         #[test]
         fn test_scan_backup_start() {
@@ -409,6 +1042,77 @@ pub mod scanners {
             assert_eq!(result.unwrap(), (expected_msg, expected_time));
         }
 
+        /// [21:07:11] [Server thread/INFO] [minecraft/DedicatedServer]: [Not Secure] <.Steve> hi from bedrock
+        #[test]
+        fn test_scan_line_chat_bedrock() {
+            use super::*;
+
+            let scan_msg = r#"[21:07:11] [Server thread/INFO] [minecraft/DedicatedServer]: [Not Secure] <.Steve> hi from bedrock"#;
+            let scan_option = ScannedLine {
+                time_str: "21:07:11".to_string(),
+                sender_thread: "Server thread".to_string(),
+                level: "INFO".to_string(),
+                sender_handle: ".Steve".to_string(),
+                is_chat_msg: true,
+                message: "hi from bedrock".to_string(),
+            };
+            let result = scan_line(scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), scan_option);
+            assert_eq!(strip_bedrock_prefix(".Steve", "."), Some("Steve"));
+        }
+
+        /// [minecraft/DedicatedServer]: .Steve joined the game
+        #[test]
+        fn test_scan_user_login_bedrock() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let scan_msg = r#".Steve joined the game"#;
+
+            let expected_name = ".Steve".to_string();
+            let expected_msg = FromServerLog::UserLogin {
+                name: expected_name.clone()
+            };
+
+            let result = scan_user_login(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), (expected_msg, expected_name));
+            assert_eq!(strip_bedrock_prefix("Davidminer_MC", "."), None);
+            assert_eq!(strip_bedrock_prefix(".Steve", ""), None);
+        }
+
+        /// [21:07:11] [Server thread/INFO] [minecraft/MinecraftServer]: {"text":"","extra":[{"text":"[Broadcast] ","color":"gold"},...]}
+        #[test]
+        fn test_scan_json_chat() {
+            use super::*;
+
+            let scan_msg = r#"[21:07:11] [Server thread/INFO] [minecraft/MinecraftServer]: {"text":"","extra":[{"text":"[Broadcast] ","color":"gold"},{"text":"§aRestart in ","extra":[{"text":"§l5 minutes","bold":true}]},"!"]}"#;
+            let scanned_line = scan_line(scan_msg).unwrap();
+
+            assert!(!scanned_line.is_chat_msg);
+            assert_eq!(scan_json_chat(scanned_line.sender_handle.as_str(), scanned_line.message.as_str()).unwrap(), "[Broadcast] Restart in 5 minutes!");
+            assert!(scan_json_chat("minecraft/MinecraftServer", "Done (12.345s)! For help, type \"help\"").is_err());
+            assert!(scan_json_chat("minecraft/MinecraftServer", r#"{"text":""}"#).is_err());
+        }
+
+        /// [21:07:11] [Server thread/WARN] [dynmap/]: {"worlds":["world"],"updates":3}
+        #[test]
+        fn test_scan_json_chat_plugin_log() {
+            use super::*;
+
+            let scan_msg = r#"[21:07:11] [Server thread/WARN] [dynmap/]: {"worlds":["world"],"updates":3}"#;
+            let scanned_line = scan_line(scan_msg).unwrap();
+
+            assert!(scan_json_chat(scanned_line.sender_handle.as_str(), scanned_line.message.as_str()).is_err());
+            assert!(scan_json_chat("minecraft/MinecraftServer", r#"{"worlds":["world"],"updates":3}"#).is_err());
+            assert!(scan_json_chat("minecraft/MinecraftServer", "[1, 2, 3]").is_err());
+            assert!(scan_json_chat("dynmap/", r#"{"text":"Rendering done"}"#).is_err());
+        }
+
         /// [minecraft/DedicatedServer]: Davidminer_MC joined the game
         #[test]
         fn test_scan_user_login() {
@@ -448,30 +1152,221 @@ pub mod scanners {
             assert!(result.is_ok());
             assert_eq!(result.unwrap(), (expected_msg, expected_name));
         }
+
+        /// [minecraft/DedicatedServer]: szmarci07iq fell from a high place
+        #[test]
+        fn test_scan_player_death() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let scan_msg = r#"szmarci07iq fell from a high place"#;
+
+            let expected_name = "szmarci07iq".to_string();
+            let expected_cause = "fell from a high place".to_string();
+            let expected_msg = FromServerLog::PlayerDeath {
+                name: expected_name.clone(),
+                cause: expected_cause.clone()
+            };
+
+            let result = scan_player_death(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), (expected_msg, expected_name, expected_cause));
+        }
+
+        /// [minecraft/DedicatedServer]: Kicked Kistepsi: Spamming the chat
+        #[test]
+        fn test_scan_player_kick() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let scan_msg = r#"Kicked Kistepsi: Spamming the chat"#;
+
+            let expected_name = "Kistepsi".to_string();
+            let expected_msg = FromServerLog::PlayerKicked {
+                name: expected_name.clone(),
+                reason: "Spamming the chat".to_string()
+            };
+
+            let result = scan_player_kick(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), (expected_msg, expected_name));
+        }
+
+        /// [minecraft/DedicatedServer]: [Rcon: Kicked Kistepsi]
+        #[test]
+        fn test_scan_player_kick_no_reason() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let scan_msg = r#"[Rcon: Kicked Kistepsi]"#;
+
+            let expected_name = "Kistepsi".to_string();
+            let expected_msg = FromServerLog::PlayerKicked {
+                name: expected_name.clone(),
+                reason: "".to_string()
+            };
+
+            let result = scan_player_kick(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), (expected_msg, expected_name));
+            assert!(scan_player_kick(scan_sender, "Kicked out of the party, Kistepsi").is_err());
+        }
+
+        /// [minecraft/NetHandlerLoginServer]: Disconnecting com.mojang.authlib.GameProfile@...: You are not white-listed on this server!
+        #[test]
+        fn test_scan_whitelist_rejection() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/NetHandlerLoginServer";
+            let scan_msg = r#"Disconnecting com.mojang.authlib.GameProfile@6f1b3c2a[id=<null>,name=Kistepsi,properties={},legacy=false] (/192.168.1.20:53412): You are not white-listed on this server!"#;
+
+            let expected_name = "Kistepsi".to_string();
+            let expected_msg = FromServerLog::WhitelistRejected { name: expected_name.clone() };
+
+            let result = scan_whitelist_rejection(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), (expected_msg, expected_name.clone()));
+
+            let result = scan_whitelist_rejection("", "Disconnecting Kistepsi (/192.168.1.20:53412): You are not whitelisted on this server!");
+            assert_eq!(result.unwrap(), (FromServerLog::WhitelistRejected { name: expected_name.clone() }, expected_name));
+
+            assert!(scan_whitelist_rejection(scan_sender, "Disconnecting Kistepsi (/192.168.1.20:53412): Timed out").is_err());
+        }
+
+        /// [minecraft/DedicatedServer]: [Davidminer_MC: Banned szmarci07iq: Griefing]
+        #[test]
+        fn test_scan_player_ban() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let scan_msg = r#"[Davidminer_MC: Banned szmarci07iq: Griefing]"#;
+
+            let expected_name = "szmarci07iq".to_string();
+            let expected_msg = FromServerLog::PlayerBanned {
+                name: expected_name.clone(),
+                reason: "Griefing".to_string()
+            };
+
+            let result = scan_player_ban(scan_sender, scan_msg);
+
+            assert!(result.is_ok());
+            assert_eq!(result.unwrap(), (expected_msg, expected_name));
+        }
+
+        #[test]
+        fn test_scan_player_death_causes() {
+            use super::*;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let deaths = [
+                ("Kistepsi drowned", "drowned"),
+                ("Kistepsi was slain by Zombie", "was slain by Zombie"),
+                ("Davidminer_MC was blown up by Creeper", "was blown up by Creeper"),
+                ("Davidminer_MC was shot by Skeleton using Bow", "was shot by Skeleton using Bow"),
+                ("szmarci07iq tried to swim in lava to escape Blaze", "tried to swim in lava to escape Blaze"),
+                ("szmarci07iq was roasted in dragon's breath", "was roasted in dragon's breath"),
+                ("Kistepsi hit the ground too hard", "hit the ground too hard"),
+                ("Kistepsi died", "died"),
+            ];
+
+            for (scan_msg, expected_cause) in deaths.iter() {
+                let result = scan_player_death(scan_sender, scan_msg);
+
+                assert!(result.is_ok(), "{}", scan_msg);
+                assert_eq!(result.unwrap().2, expected_cause.to_string());
+            }
+        }
+
+        /// [minecraft/DedicatedServer]: Kistepsi has made the advancement [Stone Age]
+        #[test]
+        fn test_scan_advancement() {
+            use super::*;
+            use super::super::FromServerLog;
+
+            let scan_sender = "minecraft/DedicatedServer";
+            let advancements = [
+                ("Kistepsi has made the advancement [Stone Age]", "has made the advancement", "[Stone Age]"),
+                ("Davidminer_MC has completed the challenge [Monsters Hunted]", "has completed the challenge", "[Monsters Hunted]"),
+                ("szmarci07iq has reached the goal [Sky's the Limit]", "has reached the goal", "[Sky's the Limit]"),
+            ];
+
+            for (scan_msg, expected_verb, expected_advancement) in advancements.iter() {
+                let name = scan_msg.split(' ').next().unwrap().to_string();
+                let expected_msg = FromServerLog::Advancement {
+                    name: name.clone(),
+                    verb: expected_verb.to_string(),
+                    advancement: expected_advancement.to_string()
+                };
+
+                let result = scan_advancement(scan_sender, scan_msg);
+
+                assert!(result.is_ok(), "{}", scan_msg);
+                assert_eq!(result.unwrap(), (expected_msg, name, expected_advancement.to_string()));
+            }
+
+            assert!(scan_advancement(scan_sender, "<Kistepsi> has made the advancement [Stone Age]").is_err());
+            assert!(scan_advancement(scan_sender, "Kistepsi has made the advancement").is_err());
+        }
+
+        #[test]
+        fn test_scan_player_death_lookalikes() {
+            use super::*;
+
+            assert!(scan_player_death("minecraft/DedicatedServer", "Kistepsi left the game").is_err());
+            assert!(scan_player_death("minecraft/DedicatedServer", "Kistepsi fellow traveller").is_err());
+            assert!(scan_player_death("minecraft/DedicatedServer", "[Server] Kistepsi died").is_err());
+            assert!(scan_player_death("TombManyGraves", "szmarci07iq died in dimension 0").is_err());
+        }
     }
 }
 
 use scanners::*;
 
-pub fn server_log_thread(_config: Value, output: ChildStdout, log_send: Sender<FromServerLog>) -> Result<(), Box<dyn Error>> {
+/// Scans the server's output line by line.
+/// With `unparsed_are_errors`, lines that aren't log lines are checked for failures, for stderr where the JVM reports launch failures.
+/// The rest are passed on as `StderrOutput`.
+pub fn server_log_thread(config: Config, output: impl Read, log_send: Sender<FromServerLog>, unparsed_are_errors: bool) -> Result<(), Box<dyn Error>> {
     info!("Server thread is now running.");
 
+    let start_trigger = StartTrigger::from_config(&config);
+
     let buf_read = BufReader::new(output);
     
     for line in buf_read.lines() {
         let line = line?;
         if let Ok(scanned_line) = scan_line(line.as_str()) {
+            let time = parse_log_time(scanned_line.time_str.as_str(), Local::now()).unwrap_or_else(|_| Local::now());
+
             if scanned_line.is_chat_msg {
                 let ScannedLine { sender_handle: name, message, .. } = scanned_line;
-                
+
                 info!(target: "server_chat", "<{}>: {}", name, message);
-                log_send.send(FromServerLog::ChatMessage { name, message })?;
+                log_send.send(FromServerLog::ChatMessage { name, message, time })?;
 
                 continue;
             }
-            
+
             let ScannedLine { sender_handle, message, .. } = scanned_line;
 
+            // Broadcasts are logged as info, warnings and errors with JSON in them are left for the error handling
+            if scanned_line.level == "INFO" {
+                if let Ok(message) = scan_json_chat(sender_handle.as_str(), message.as_str()) {
+                    info!(target: "server_chat", "<Broadcast>: {}", message);
+                    log_send.send(FromServerLog::ChatMessage { name: "Broadcast".to_string(), message, time })?;
+
+                    continue;
+                }
+            }
+
             let level = match scanned_line.level.as_str() {
                 "INFO" => Level::Info,
                 "WARN" => Level::Warn,
@@ -496,20 +1391,38 @@ pub fn server_log_thread(_config: Value, output: ChildStdout, log_send: Sender<F
                 }
             }
 
+            let started = match &start_trigger {
+                Some(trigger) => trigger.scan(sender_handle.as_str(), message.as_str()),
+                None => scan_server_start(sender_handle.as_str(), message.as_str())
+                    .or_else(|_| scan_server_start_vanilla(sender_handle.as_str(), message.as_str())),
+            };
+            if let Ok(msg) = started {
+                log_send.send(msg)?;
+                info!(target: "server_status", "Server is now up");
+                continue;
+            }
+
             simple_scan!(
-                scan_server_start => [Level::Info] "server_status": "Server is now up";
+                scan_server_version => [Level::Info] "server_status": "Server version {}", version;
                 scan_server_stop => [Level::Info] "server_status": "Server is now stopping";
+                scan_server_stopped => [Level::Info] "server_status": "Server saved the worlds";
                 scan_lag_spike => [Level::Warn] "server_status": "Server overloaded! Lagspike of {} ms", length;
                 scan_backup_start => [Level::Info] "server_status": "Backup started";
                 scan_backup_stop => [Level::Info] "server_status": "Backup finished in {}", duration;
                 scan_user_login => [Level::Info] "server_chat": "{} joined the game", name;
-                scan_user_logout => [Level::Info] "server_chat": "{} left the game", name
+                scan_user_logout => [Level::Info] "server_chat": "{} left the game", name;
+                scan_player_death => [Level::Info] "server_chat": "{} {}", name, cause;
+                scan_advancement => [Level::Info] "server_chat": "{} made the advancement {}", name, advancement;
+                scan_player_kick => [Level::Warn] "server_chat": "{} was kicked", name;
+                scan_whitelist_rejection => [Level::Info] "server_chat": "{} isn't whitelisted", name;
+                scan_player_ban => [Level::Warn] "server_chat": "{} was banned", name
             );
 
             if level <= Level::Error {
                 let error_msg = FromServerLog::ServerError {
                     exception: message.clone(),
-                    sender: sender_handle.clone()
+                    sender: sender_handle.clone(),
+                    time
                 };
                 log_send.send(error_msg)?;
             }
@@ -530,6 +1443,21 @@ pub fn server_log_thread(_config: Value, output: ChildStdout, log_send: Sender<F
                 )
             }
         } else {
+            if unparsed_are_errors {
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                if let Ok(msg) = scan_stderr_failure(line.as_str()) {
+                    error!(target: "server_log", "[stderr]: {}", line);
+                    log_send.send(msg)?;
+                } else {
+                    warn!(target: "server_log", "[stderr]: {}", line);
+                    log_send.send(FromServerLog::StderrOutput { line })?;
+                }
+                continue;
+            }
+
             if CONSOLE_ENABLED.load(Ordering::Relaxed) {
                 error!(target: "server_log", "[Stack Trace]: {}", line);
                 continue;
@@ -540,4 +1468,80 @@ pub fn server_log_thread(_config: Value, output: ChildStdout, log_send: Sender<F
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_tail() {
+        let path = std::env::temp_dir().join("server_maintainer_test_latest.log");
+        fs::write(&path, "[21:07:11] [Server thread/INFO] [minecraft/DedicatedServer]: <Kistepsi> old\n").unwrap();
+
+        let active = Arc::new(AtomicBool::new(true));
+        let mut lines = BufReader::new(LogTail::open(&path, active.clone()).unwrap()).lines();
+
+        fs::OpenOptions::new().append(true).open(&path).unwrap()
+            .write_all(b"[21:07:12] [Server thread/INFO] [minecraft/DedicatedServer]: <Kistepsi> new\n").unwrap();
+        assert!(lines.next().unwrap().unwrap().ends_with("<Kistepsi> new"));
+
+        fs::write(&path, "[21:07:13] [Server thread/INFO]: rotated\n").unwrap();
+        assert!(lines.next().unwrap().unwrap().ends_with("rotated"));
+
+        active.store(false, Ordering::Relaxed);
+        assert!(lines.next().is_none());
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_log_tail_missing_file() {
+        let path = std::env::temp_dir().join("server_maintainer_test_missing.log");
+        fs::remove_file(&path).ok();
+
+        let active = Arc::new(AtomicBool::new(true));
+        let mut lines = BufReader::new(LogTail::open(&path, active.clone()).unwrap()).lines();
+
+        fs::write(&path, "[21:07:11] [Server thread/INFO]: Starting minecraft server version 1.16.5\n").unwrap();
+        assert!(lines.next().unwrap().unwrap().ends_with("version 1.16.5"));
+
+        active.store(false, Ordering::Relaxed);
+        fs::remove_file(&path).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_log_tail_rename() {
+        let path = std::env::temp_dir().join("server_maintainer_test_rename.log");
+        let rotated_path = std::env::temp_dir().join("server_maintainer_test_rename.log.1");
+        fs::write(&path, "[21:07:11] [Server thread/INFO]: old\n").unwrap();
+
+        let active = Arc::new(AtomicBool::new(true));
+        let mut lines = BufReader::new(LogTail::open(&path, active.clone()).unwrap()).lines();
+
+        fs::rename(&path, &rotated_path).unwrap();
+        fs::write(&path, "[21:07:12] [Server thread/INFO]: a new log longer than the old one\n").unwrap();
+        assert!(lines.next().unwrap().unwrap().ends_with("longer than the old one"));
+
+        active.store(false, Ordering::Relaxed);
+        fs::remove_file(&path).ok();
+        fs::remove_file(&rotated_path).ok();
+    }
+
+    #[test]
+    fn test_stderr_warnings_are_not_errors() {
+        let stderr = "WARNING: An illegal reflective access operation has occurred\n\
+            SLF4J: Failed to load class \"org.slf4j.impl.StaticLoggerBinder\".\n\
+            \n\
+            Error: Could not find or load main class net.minecraft.server.Main\n";
+        let (log_send, log_recv) = crossbeam::channel::unbounded();
+
+        server_log_thread(Config::default(), stderr.as_bytes(), log_send, true).unwrap();
+        let messages: Vec<FromServerLog> = log_recv.try_iter().collect();
+
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(&messages[0], FromServerLog::StderrOutput { line } if line.starts_with("WARNING:")));
+        assert!(matches!(&messages[1], FromServerLog::StderrOutput { line } if line.starts_with("SLF4J:")));
+        assert!(matches!(&messages[2], FromServerLog::ServerError { exception, .. } if exception.contains("Could not find or load main class")));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn properties_path(server_folder: &str) -> PathBuf {
+    Path::new(server_folder).join("server.properties")
+}
+
+/// Parses `key=value` lines, skipping comments and blank lines.
+pub fn parse_properties(contents: &str) -> HashMap<String, String> {
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .filter_map(|line| {
+            let (key, value) = line.split_at(line.find('=')?);
+            Some((key.trim().to_string(), value[1..].to_string()))
+        })
+        .collect()
+}
+
+pub fn read_properties(server_folder: &str) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    Ok(parse_properties(fs::read_to_string(properties_path(server_folder))?.as_str()))
+}
+
+/// Escapes a value the way Java writes `.properties` files.
+pub fn escape_value(value: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped += "\\\\",
+            '\n' | '\r' => escaped.push(' '),
+            c if c.is_ascii() => escaped.push(c),
+            c => {
+                let mut buf = [0u16; 2];
+                for unit in c.encode_utf16(&mut buf) {
+                    escaped += format!("\\u{:04x}", unit).as_str();
+                }
+            }
+        }
+    }
+
+    escaped
+}
+
+pub fn unescape_value(value: &str) -> String {
+    let mut units = Vec::<u16>::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u16; 2];
+            units.extend_from_slice(c.encode_utf16(&mut buf));
+            continue;
+        }
+
+        match chars.next() {
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                units.extend(u16::from_str_radix(hex.as_str(), 16).ok());
+            },
+            Some('n') => units.push(b'\n' as u16),
+            Some('t') => units.push(b'\t' as u16),
+            Some(c) => {
+                let mut buf = [0u16; 2];
+                units.extend_from_slice(c.encode_utf16(&mut buf));
+            },
+            None => ()
+        }
+    }
+
+    String::from_utf16_lossy(&units)
+}
+
+/// Replaces the value of `key`, keeping every other line intact. Appends the key if it is missing.
+pub fn set_property(contents: &str, key: &str, value: &str) -> String {
+    let mut found = false;
+    let mut lines: Vec<String> = contents.lines()
+        .map(|line| {
+            let is_key = line.split('=').next().map(str::trim) == Some(key) && line.contains('=');
+            if is_key && !found {
+                found = true;
+                format!("{}={}", key, escape_value(value))
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+
+    if !found {
+        lines.push(format!("{}={}", key, escape_value(value)));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+pub fn write_property(server_folder: &str, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    let path = properties_path(server_folder);
+    let contents = fs::read_to_string(&path)?;
+    fs::write(&path, set_property(contents.as_str(), key, value))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_properties() {
+        let contents = "#Minecraft server properties\n#Sat Nov 14 21:00:00 CET 2020\nmax-players=20\nmotd=A Minecraft Server\nlevel-seed=\n";
+        let properties = parse_properties(contents);
+
+        assert_eq!(properties.get("max-players").map(String::as_str), Some("20"));
+        assert_eq!(properties.get("motd").map(String::as_str), Some("A Minecraft Server"));
+        assert_eq!(properties.get("level-seed").map(String::as_str), Some(""));
+        assert_eq!(properties.len(), 3);
+    }
+
+    #[test]
+    fn test_set_property() {
+        let contents = "#Minecraft server properties\nmax-players=20\nmotd=A Minecraft Server\n";
+        let expected = "#Minecraft server properties\nmax-players=20\nmotd=Welcome \\u00e1 \\\\o/\n";
+
+        assert_eq!(set_property(contents, "motd", "Welcome á \\o/"), expected);
+        assert_eq!(unescape_value("Welcome \\u00e1 \\\\o/"), "Welcome á \\o/");
+    }
+
+    #[test]
+    fn test_set_property_missing() {
+        let contents = "max-players=20\n";
+
+        assert_eq!(set_property(contents, "motd", "Hi"), "max-players=20\nmotd=Hi\n");
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Duration, Local};
+
+/// What the bot knows about the server. The main thread keeps it up to date as events come in,
+/// and reads it back for commands and the presence, other threads like the metrics endpoint only read it.
+#[derive(Clone, Debug, Default)]
+pub struct ServerState {
+    pub status: &'static str,
+    pub online_players: HashSet<String>,
+    pub max_players: Option<usize>,
+    pub running_since: Option<DateTime<Local>>,
+    /// When the last few backups finished and how long they took, oldest first.
+    pub backup_history: VecDeque<(DateTime<Local>, Duration)>,
+    /// The Minecraft version, from the startup log.
+    pub version: Option<String>,
+    pub scheduled_shutdown: Option<DateTime<Local>>,
+    pub lag_spikes: u64,
+    pub backups: u64,
+    pub bot_version: &'static str,
+}
+
+pub type SharedState = Arc<RwLock<ServerState>>;
+
+impl ServerState {
+    pub fn shared(bot_version: &'static str) -> SharedState {
+        Arc::new(RwLock::new(ServerState {
+            bot_version,
+            ..ServerState::default()
+        }))
+    }
+
+    pub fn uptime(&self, now: DateTime<Local>) -> Option<Duration> {
+        self.running_since.map(|since| now - since)
+    }
+
+    pub fn last_backup(&self) -> Option<(DateTime<Local>, Duration)> {
+        self.backup_history.back().copied()
+    }
+
+    /// Keeps the last `history_len` backups.
+    pub fn record_backup(&mut self, finish_time: DateTime<Local>, time: Duration, history_len: usize) {
+        self.backups += 1;
+        self.backup_history.push_back((finish_time, time));
+        while self.backup_history.len() > history_len {
+            self.backup_history.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_backup() {
+        let now = Local::now();
+        let mut state = ServerState::default();
+        for secs in 1..=4 {
+            state.record_backup(now + Duration::seconds(secs), Duration::seconds(secs), 3);
+        }
+
+        assert_eq!(state.backups, 4);
+        assert_eq!(state.backup_history.len(), 3);
+        assert_eq!(state.backup_history.front().map(|(_, time)| time.num_seconds()), Some(2));
+        assert_eq!(state.last_backup(), Some((now + Duration::seconds(4), Duration::seconds(4))));
+    }
+}
@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Memory of the whole system, in kB.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SystemMemory {
+    pub total: u64,
+    pub available: u64,
+}
+
+fn parse_kb_field(contents: &str, field: &str) -> Option<u64> {
+    contents.lines()
+        .find_map(|line| line.strip_prefix(field))
+        .and_then(|rest| rest.trim_start_matches(':').trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+pub fn parse_meminfo(meminfo: &str) -> Option<SystemMemory> {
+    Some(SystemMemory {
+        total: parse_kb_field(meminfo, "MemTotal")?,
+        available: parse_kb_field(meminfo, "MemAvailable")?,
+    })
+}
+
+/// Reads system memory from `/proc/meminfo`, not available outside Linux.
+pub fn system_memory() -> Option<SystemMemory> {
+    parse_meminfo(fs::read_to_string("/proc/meminfo").ok()?.as_str())
+}
+
+/// Reads the resident memory of a process in kB from `/proc/<pid>/status`, not available outside Linux.
+pub fn process_memory(pid: u32) -> Option<u64> {
+    parse_kb_field(fs::read_to_string(format!("/proc/{}/status", pid)).ok()?.as_str(), "VmRSS")
+}
+
+/// Joins the NUL-separated arguments of `/proc/<pid>/cmdline` with spaces.
+pub fn parse_cmdline(cmdline: &[u8]) -> String {
+    String::from_utf8_lossy(cmdline).split('\0').filter(|x| !x.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+/// The command line of a process, `None` if it doesn't exist. Not available outside Linux.
+pub fn process_command_line(pid: u32) -> Option<String> {
+    Some(parse_cmdline(&fs::read(format!("/proc/{}/cmdline", pid)).ok()?))
+}
+
+/// Whether a process with this PID exists, `None` if there's no way to check. Uses `/proc` on Linux,
+/// `tasklist` on Windows and `kill -0` elsewhere.
+pub fn process_alive(pid: u32) -> Option<bool> {
+    if Path::new("/proc/self").exists() {
+        return Some(Path::new(&format!("/proc/{}", pid)).exists());
+    }
+
+    if cfg!(windows) {
+        let output = Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .stdin(Stdio::null())
+            .output().ok()?;
+        Some(parse_tasklist(&String::from_utf8_lossy(&output.stdout), pid))
+    } else {
+        let status = Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .status().ok()?;
+        Some(status.success())
+    }
+}
+
+/// Whether `tasklist /FO CSV` output lists the PID, it prints an info line instead if nothing matched.
+pub fn parse_tasklist(output: &str, pid: u32) -> bool {
+    let pid = format!("\"{}\"", pid);
+    output.lines().any(|line| line.split(',').nth(1) == Some(pid.as_str()))
+}
+
+/// CPU time from the aggregate `cpu` line of `/proc/stat`, as `(idle, total)` in ticks.
+pub fn parse_cpu_times(stat: &str) -> Option<(u64, u64)> {
+    let times: Vec<u64> = stat.lines()
+        .find(|line| line.starts_with("cpu "))?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|x| x.parse().ok())
+        .collect();
+
+    // idle + iowait count as idle
+    let idle = times.get(3)? + times.get(4).unwrap_or(&0);
+    Some((idle, times.iter().sum()))
+}
+
+pub fn cpu_times() -> Option<(u64, u64)> {
+    parse_cpu_times(fs::read_to_string("/proc/stat").ok()?.as_str())
+}
+
+/// Ticks a process spent on the CPU, `utime + stime` from `/proc/<pid>/stat`.
+pub fn parse_process_cpu_time(stat: &str) -> Option<u64> {
+    // The process name may contain spaces, the fields after it are fixed
+    let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+
+    Some(fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?)
+}
+
+pub fn process_cpu_time(pid: u32) -> Option<u64> {
+    parse_process_cpu_time(fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?.as_str())
+}
+
+/// The 1, 5 and 15 minute load averages from `/proc/loadavg`.
+pub fn load_average() -> Option<String> {
+    let loadavg = fs::read_to_string("/proc/loadavg").ok()?;
+    Some(loadavg.split_whitespace().take(3).collect::<Vec<_>>().join(" "))
+}
+
+pub fn format_kb(kb: u64) -> String {
+    if kb >= 1024 * 1024 {
+        format!("{:.1}GB", kb as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{}MB", kb / 1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_meminfo() {
+        let meminfo = "MemTotal:       16314616 kB\nMemFree:         1200340 kB\nMemAvailable:    9123456 kB\n";
+        let expected = SystemMemory {
+            total: 16314616,
+            available: 9123456,
+        };
+
+        assert_eq!(parse_meminfo(meminfo), Some(expected));
+    }
+
+    #[test]
+    fn test_parse_cpu_times() {
+        let stat = "cpu  4705 356 584 3699 23 23 0 0 0 0\ncpu0 1393 280 234 852 6 6 0 0 0 0\n";
+        assert_eq!(parse_cpu_times(stat), Some((3722, 9390)));
+
+        let process_stat = "1234 (java server) S 1 1234 1234 0 -1 4194304 25000 0 0 0 1500 300 0 0 20 0 50 0 12345";
+        assert_eq!(parse_process_cpu_time(process_stat), Some(1800));
+    }
+
+    #[test]
+    fn test_parse_cmdline() {
+        assert_eq!(parse_cmdline(b"java\0-Xmx4G\0-jar\0server.jar\0nogui\0"), "java -Xmx4G -jar server.jar nogui");
+        assert_eq!(parse_cmdline(b""), "");
+    }
+
+    #[test]
+    fn test_parse_tasklist() {
+        let output = "\"java.exe\",\"4120\",\"Console\",\"1\",\"4,194,304 K\"\r\n";
+
+        assert!(parse_tasklist(output, 4120));
+        assert!(!parse_tasklist(output, 412));
+        assert!(!parse_tasklist("INFO: No tasks are running which match the specified criteria.\r\n", 4120));
+    }
+
+    #[test]
+    fn test_process_alive() {
+        assert_eq!(process_alive(std::process::id()), Some(true));
+    }
+
+    #[test]
+    fn test_parse_vm_rss() {
+        let status = "Name:\tjava\nVmPeak:\t 6000000 kB\nVmRSS:\t 4194304 kB\n";
+
+        assert_eq!(parse_kb_field(status, "VmRSS"), Some(4194304));
+        assert_eq!(format_kb(4194304), "4.0GB");
+    }
+}
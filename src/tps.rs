@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Local};
+
+use crate::message_format::minecraft_formatting;
+
+/// Finds the overall TPS in the output of a TPS command, eg.
+/// `Overall: Mean tick time: 12.345 ms. Mean TPS: 20.000` from Forge or
+/// `TPS from last 1m, 5m, 15m: 19.8, 20.0, 20.0` from Paper and Spark.
+pub fn parse_tps(output: &str) -> Option<f64> {
+    let output = minecraft_formatting(output, false);
+
+    let number_after = |line: &str, marker: &str| -> Option<f64> {
+        let rest = &line[line.find(marker)? + marker.len()..];
+        rest.split(|c: char| c == ',' || c.is_whitespace())
+            .map(|x| x.trim_start_matches('*'))
+            .find(|x| !x.is_empty())?
+            .parse().ok()
+    };
+
+    if let Some(overall) = output.lines().find(|line| line.contains("Overall")) {
+        return number_after(overall, "Mean TPS:");
+    }
+
+    output.lines()
+        .find(|line| line.contains("TPS from last"))
+        .and_then(|line| number_after(line, ":"))
+}
+
+/// The TPS samples of the last `duration`, to tell sustained lag from a single bad sample.
+pub struct TpsWindow {
+    samples: VecDeque<(DateTime<Local>, f64)>,
+    duration: Duration,
+}
+
+impl TpsWindow {
+    pub fn new(duration: Duration) -> TpsWindow {
+        TpsWindow {
+            samples: VecDeque::new(),
+            duration,
+        }
+    }
+
+    pub fn push(&mut self, time: DateTime<Local>, tps: f64) {
+        self.samples.push_back((time, tps));
+
+        // Keep one sample older than the window, so a full window can be told apart from a short one
+        while self.samples.len() > 1 && time - self.samples[1].0 >= self.duration {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Whether every sample for the whole window was below `threshold`.
+    pub fn is_sustained_low(&self, threshold: f64) -> bool {
+        match (self.samples.front(), self.samples.back()) {
+            (Some((first, _)), Some((last, _))) => *last - *first >= self.duration
+                && self.samples.iter().all(|(_, tps)| *tps < threshold),
+            _ => false
+        }
+    }
+
+    pub fn average(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        Some(self.samples.iter().map(|(_, tps)| tps).sum::<f64>() / self.samples.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tps() {
+        let forge = "Dim   0 (overworld): Mean tick time: 10.000 ms. Mean TPS: 20.000\nOverall: Mean tick time: 62.500 ms. Mean TPS: 16.000";
+        assert_eq!(parse_tps(forge), Some(16.0));
+
+        let paper = "§6TPS from last 1m, 5m, 15m: §a*19.8, §a20.0, §a20.0";
+        assert_eq!(parse_tps(paper), Some(19.8));
+
+        assert_eq!(parse_tps("Unknown command"), None);
+    }
+
+    #[test]
+    fn test_sustained_low() {
+        let start = Local::now();
+        let mut window = TpsWindow::new(Duration::minutes(5));
+
+        for minute in 0..5 {
+            window.push(start + Duration::minutes(minute), 15.0);
+            assert!(!window.is_sustained_low(18.0));
+        }
+        window.push(start + Duration::minutes(5), 15.0);
+        assert!(window.is_sustained_low(18.0));
+
+        window.push(start + Duration::minutes(6), 19.5);
+        assert!(!window.is_sustained_low(18.0));
+        assert!(window.average().unwrap() > 15.0);
+    }
+}